@@ -0,0 +1,371 @@
+/*----------------------------------------------------------------------
+Autoplay module
+
+Auto play using a choice of algorithms.  A strategy is given the
+position and the moves it may make, and picks one.  The driver plays
+from a position until the game is won, no move is left, the strategy
+gives up, or a limit on moves is reached, and gives back the moves.
+
+The driver never offers a move back to a position already reached in
+the game, with piles swapped or not, and counting recycles as the
+solver does.  When every move would go back, the game is stuck.  That
+stops going round in circles, but not wandering, such as moving cards
+about the tableau in ever new ways between draws.  So the game is also
+stuck after a run of moves with no progress.  Progress is fewer cards
+face down or in the stock and waste, or more on the foundations than
+ever before.  Each of those can only happen so often, so a game always
+ends.
+
+Strategies
+    random      any move, from a seeded generator
+    greedy      to foundation first, then onto the tableau turning up a
+                card or from the waste, then a draw, then other
+                tableau moves, taking the first of each
+    heuristic   the best by the rules of thumb of the hint module
+    solver      follows a line from the solver, solving again when
+                there is none or it is no longer on offer, the
+                heuristic choice when the solver runs out of budget,
+                and gives up when the solver finds the game lost
+    hindsight   the move that wins the most samples of the hidden
+                cards, see the hindsight module, the heuristic choice
+                among those that win as many.  More samples play
+                stronger.
+
+The solver strategy in thoughtful mode knows the face down cards, so
+it shows how many deals could be won, not how many a player would.
+In normal mode it only plays lines certain to win and solves again
+after each move, which costs a search per move.
+
+Over a range of deal numbers the results are tallied, so strategies
+can be compared on the same deals.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use std::collections::HashSet;
+
+use crate::deck::*;
+use crate::hindsight::*;
+use crate::hint::*;
+use crate::klondike::*;
+use crate::layout::*;
+use crate::misc::*;
+use crate::solver::*;
+
+pub trait Strategy {
+    fn name(&self) -> String;
+    // moves is not empty, None gives up
+    fn choose(&mut self, rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move])
+        -> Option<Move>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum End {
+    Won,
+    Stuck,                              // no move left
+    Resigned,                           // strategy gave up
+    Limit,                              // too many moves
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Playout {
+    pub moves : Vec<Move>,
+    pub end   : End,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub played : u64,
+    pub won    : u64,
+    pub stuck  : u64,
+    pub moves  : u64,                   // in all games
+}
+
+/*----------------------------------------------------------------------
+Strategies
+*/
+
+#[derive(Clone, Debug)]
+pub struct Random {
+    rng : SplitMix64,
+}
+
+impl Random {
+pub fn new(seed:u64) -> Random {
+    Random { rng: SplitMix64::new(seed) }
+}}
+
+impl Strategy for Random {
+fn name(&self) -> String { "random".to_string() }
+fn choose(&mut self, rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move])
+    -> Option<Move>
+{
+    Some(moves[self.rng.below(moves.len() as u64) as usize])
+}}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Greedy;
+
+impl Strategy for Greedy {
+fn name(&self) -> String { "greedy".to_string() }
+fn choose(&mut self, rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move])
+    -> Option<Move>
+{
+    let class = |mv:&&Move| match (role(mv.from), role(mv.to)) {
+        (Role::Foundation(_), _) => 5,
+        (_, Role::Foundation(_)) => 0,
+        (Role::Waste, _) => 1,
+        (_, Role::Tableau(_)) if turns_up(lo, mv, recycles) => 1,
+        _ if mv.is_draw() => 2,
+        (_, Role::Tableau(_)) => 3,
+        _ => 4,
+    };
+    moves.iter().min_by_key(class).copied()
+}}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Heuristic;
+
+impl Strategy for Heuristic {
+fn name(&self) -> String { "heuristic".to_string() }
+fn choose(&mut self, rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move])
+    -> Option<Move>
+{
+    moves.iter().min_by_key(|mv| -reason(rules, lo, mv).1).copied()
+}}
+
+#[derive(Clone, Debug)]
+pub struct SolverStrategy {
+    pub mode   : Mode,
+    pub budget : Budget,
+    plan       : Vec<Move>,             // rest of the line, next move last
+}
+
+impl SolverStrategy {
+pub fn new(mode:Mode, budget:&Budget) -> SolverStrategy {
+    SolverStrategy { mode, budget: *budget, plan: vec![] }
+}}
+
+impl Strategy for SolverStrategy {
+fn name(&self) -> String {
+    match self.mode {
+        Mode::Thoughtful => "solver".to_string(),
+        Mode::Normal => "solver-normal".to_string(),
+    }
+}
+fn choose(&mut self, rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move])
+    -> Option<Move>
+{
+    if !self.plan.last().is_some_and(|mv| moves.contains(mv)) {
+        self.plan = match rules.solve(lo, recycles, self.mode, &self.budget).outcome {
+            Outcome::Win(line) => line.into_iter().rev().collect(),
+            Outcome::Lost => return None,
+            Outcome::Unknown => vec![],
+        };
+    }
+    match self.plan.pop() {
+        Some(mv) if moves.contains(&mv) => Some(mv),
+        _ => Heuristic.choose(rules, lo, recycles, moves),
+    }
+}}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Hindsight {
+    pub sampling : Sampling,
+}
+
+impl Strategy for Hindsight {
+fn name(&self) -> String {
+    format!("hindsight-{}", self.sampling.samples)
+}
+fn choose(&mut self, rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move])
+    -> Option<Move>
+{
+    // ties go by the rules of thumb
+    let odds = rules.estimate_moves(lo, recycles, moves, &self.sampling);
+    odds.iter().rev()
+        .max_by_key(|o| (o.won, reason(rules, lo, &o.mv).1))
+        .map(|best| best.mv)
+}}
+
+/*----------------------------------------------------------------------
+Driver
+*/
+
+impl Rules {
+fn autoplay_key(&self, lo:&Layout, recycles:usize) -> u64 {
+    let recycles = match self.passes {
+        Passes::Unlimited => 0,
+        Passes::Limited(_) => recycles,
+    };
+    self.canonical_hash(lo) ^ zobrist_count(recycles)
+}}
+
+// cards face down, in the stock or waste, and on the foundations
+fn counts(lo:&Layout) -> (usize, usize) {
+    let hidden = (TABLEAU..N_PILES)
+        .map(|t| lo.pile(t).iter().filter(|c| !c.face_up()).count())
+        .sum::<usize>() + lo.pile(STOCK).len() + lo.pile(WASTE).len();
+    let up = (FOUNDATION..TABLEAU).map(|f| lo.pile(f).len()).sum();
+    (hidden, up)
+}
+
+// this many moves in a row with no progress is stuck
+pub const IDLE_MOVES:usize = 100;
+
+impl Rules {
+pub fn autoplay(&self, lo:&Layout, recycles:usize, strategy:&mut dyn Strategy,
+    max_moves:usize) -> Playout
+{
+    let mut lo = lo.clone();
+    let mut recycles = recycles;
+    let mut seen = HashSet::from([self.autoplay_key(&lo, recycles)]);
+    let mut moves = vec![];
+    let (mut hidden, mut up) = counts(&lo);
+    let mut idle = 0;
+    let end = loop {
+        if self.is_won(&lo) {
+            break End::Won;
+        }
+        if moves.len() >= max_moves {
+            break End::Limit;
+        }
+        let offered:Vec<Move> = self.legal_moves(&lo, recycles).into_iter()
+            .filter(|mv| {
+                let mut next = lo.clone();
+                self.apply(&mut next, recycles, mv).unwrap();
+                let next_recycles = recycles + mv.is_recycle() as usize;
+                !seen.contains(&self.autoplay_key(&next, next_recycles))
+            })
+            .collect();
+        if offered.is_empty() || idle >= IDLE_MOVES {
+            break End::Stuck;
+        }
+        let Some(mv) = strategy.choose(self, &lo, recycles, &offered) else {
+            break End::Resigned;
+        };
+        assert!(offered.contains(&mv), "{} chose a move not offered", strategy.name());
+        self.apply(&mut lo, recycles, &mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+        seen.insert(self.autoplay_key(&lo, recycles));
+        moves.push(mv);
+        let (now_hidden, now_up) = counts(&lo);
+        if now_hidden < hidden || now_up > up {
+            (hidden, up, idle) = (now_hidden, now_up, 0);
+        } else {
+            idle += 1;
+        }
+    };
+    Playout { moves, end }
+}}
+
+impl Rules {
+pub fn autoplay_deal(&self, deal:u64, strategy:&mut dyn Strategy, max_moves:usize)
+    -> Playout
+{
+    self.autoplay(&self.deal(&Deck::from_deal(deal)), 0, strategy, max_moves)
+}}
+
+impl Tally {
+pub fn add(&mut self, playout:&Playout) {
+    self.played += 1;
+    self.won += (End::Won == playout.end) as u64;
+    self.stuck += (End::Stuck == playout.end) as u64;
+    self.moves += playout.moves.len() as u64;
+}}
+
+impl Rules {
+pub fn benchmark(&self, deals:std::ops::Range<u64>, strategy:&mut dyn Strategy,
+    max_moves:usize) -> Tally
+{
+    let mut tally = Tally::default();
+    for deal in deals {
+        tally.add(&self.autoplay_deal(deal, strategy, max_moves));
+    }
+    tally
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[cfg(test)]
+fn check_playout(rules:&Rules, deal:u64, playout:&Playout) {
+    let mut lo = rules.deal(&Deck::from_deal(deal));
+    let mut recycles = 0;
+    for mv in &playout.moves {
+        rules.apply(&mut lo, recycles, mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+    }
+    assert_eq!(rules.is_won(&lo), End::Won == playout.end);
+}
+
+#[test]
+fn test_autoplay() {
+    let rules = Rules::default();
+    let budget = Budget { nodes: 2000, time: None };
+    let mut strategies:Vec<Box<dyn Strategy>> = vec![
+        Box::new(Random::new(7)),
+        Box::new(Greedy),
+        Box::new(Heuristic),
+        Box::new(SolverStrategy::new(Mode::Thoughtful, &budget)),
+    ];
+    for strategy in strategies.iter_mut() {
+        for deal in 0..3 {
+            let playout = rules.autoplay_deal(deal, strategy.as_mut(), 10_000);
+            assert_ne!(playout.end, End::Limit, "{} deal {}", strategy.name(), deal);
+            check_playout(&rules, deal, &playout);
+        }
+    }
+
+    // the solver wins what it can prove, see dealgen
+    let mut solver = SolverStrategy::new(Mode::Thoughtful, &budget);
+    let tally = rules.benchmark(0..3, &mut solver, 10_000);
+    assert_eq!((tally.played, tally.won), (3, 3));
+
+    // deal 4 cannot be won
+    let mut solver = SolverStrategy::new(Mode::Thoughtful, &Budget { nodes: 20_000, time: None });
+    let playout = rules.autoplay_deal(4, &mut solver, 10_000);
+    assert_eq!(playout, Playout { moves: vec![], end: End::Resigned });
+
+    // same seed, same game
+    let a = rules.autoplay_deal(5, &mut Random::new(1), 10_000);
+    let b = rules.autoplay_deal(5, &mut Random::new(1), 10_000);
+    assert_eq!(a, b);
+
+    // cut short
+    let playout = rules.autoplay_deal(5, &mut Greedy, 10);
+    assert_eq!((playout.moves.len(), playout.end), (10, End::Limit));
+}
+
+#[test]
+fn test_autoplay_hindsight() {
+    // kings buried under their queens, one card in the stock
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P1hq P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2skSQ P2hkCQ P2ckDQ P1DK P0P0P0").unwrap();
+    let sampling = Sampling { samples: 2, threads: 1, ..Default::default() };
+    let playout = rules.autoplay(&lo, 0, &mut Hindsight { sampling }, 100);
+    assert_eq!(playout.end, End::Won);
+}
+
+#[test]
+fn test_autoplay_stuck() {
+    // nothing can move, and drawing just goes round
+    let rules = Rules::default();
+    let lo = Layout::from_text("P1H5 P0 P0P0P0P0 P1S9 P0P0P0P0P0P0").unwrap();
+    let playout = rules.autoplay(&lo, 0, &mut Greedy, 100);
+    assert_eq!(playout.moves, [Move { from: STOCK, to: WASTE, n: 1 }]);
+    assert_eq!(playout.end, End::Stuck);
+}
+
+// end mod autoplay
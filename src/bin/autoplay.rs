@@ -0,0 +1,65 @@
+/*----------------------------------------------------------------------
+Play deals with one of the autoplay strategies, and tally the results.
+
+    autoplay STRATEGY COUNT [FIRST] [DRAW] [NODES] [SAMPLES]
+
+STRATEGY is random, greedy, heuristic, solver, solver-normal or
+hindsight.  Plays COUNT deal numbers from FIRST, 0 by default.  DRAW is
+the draw count, 3 by default, with unlimited passes.  NODES is the
+solver budget for each search.  SAMPLES is for hindsight, 20 by
+default.  A single deal also prints its moves.
+*/
+
+use solitaire::autoplay::*;
+use solitaire::hindsight::*;
+use solitaire::klondike::*;
+use solitaire::misc::*;
+use solitaire::solver::*;
+
+pub const MAX_MOVES:usize = 10_000;
+
+fn main() {
+    let args:Vec<String> = std::env::args().collect();
+    let arg = |i:usize, default:u64| arg_or_exit(&args, i, default);
+    if args.len() < 3 {
+        eprintln!("usage: autoplay STRATEGY COUNT [FIRST] [DRAW] [NODES] [SAMPLES]");
+        std::process::exit(1);
+    }
+    let count = arg(2, 0);
+    let first = arg(3, 0);
+    let rules = Rules::new(arg(4, 3) as usize, Passes::Unlimited);
+    let budget = Budget { nodes: arg(5, Budget::default().nodes), time: None };
+    let sampling = Sampling {
+        samples: arg(6, Sampling::default().samples as u64) as usize,
+        budget,
+        ..Default::default()
+    };
+    let mut strategy:Box<dyn Strategy> = match args[1].as_str() {
+        "random"        => Box::new(Random::new(first)),
+        "greedy"        => Box::new(Greedy),
+        "heuristic"     => Box::new(Heuristic),
+        "solver"        => Box::new(SolverStrategy::new(Mode::Thoughtful, &budget)),
+        "solver-normal" => Box::new(SolverStrategy::new(Mode::Normal, &budget)),
+        "hindsight"     => Box::new(Hindsight { sampling }),
+        name => {
+            eprintln!("no strategy {}", name);
+            std::process::exit(1);
+        }
+    };
+
+    let mut tally = Tally::default();
+    for deal in first..first.saturating_add(count) {
+        let playout = rules.autoplay_deal(deal, strategy.as_mut(), MAX_MOVES);
+        println!("deal {:>8}  {:<8}  moves {:>5}",
+            deal, format!("{:?}", playout.end).to_lowercase(), playout.moves.len());
+        if 1 == count {
+            let text:Vec<String> = playout.moves.iter().map(|mv| mv.to_text()).collect();
+            println!("{}", text.join(" "));
+        }
+        tally.add(&playout);
+    }
+    println!("{}: won {} of {} ({:.1}%), stuck {}, {:.1} moves a game",
+        strategy.name(), tally.won, tally.played,
+        100.0 * tally.won as f64 / tally.played.max(1) as f64,
+        tally.stuck, tally.moves as f64 / tally.played.max(1) as f64);
+}
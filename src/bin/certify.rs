@@ -0,0 +1,61 @@
+/*----------------------------------------------------------------------
+Make and check solver certificates, see the certificate module.
+
+    certify solve DEAL [DRAW] [NODES]
+    certify check FILE...
+
+solve prints a certificate for deal number DEAL, if the solver wins or
+finds it lost within NODES.  DRAW is the draw count, 3 by default, with
+unlimited passes.  check verifies each certificate file, and exits with
+1 if any fails.  A win is played with the rules alone.  For a loss
+every position that can be reached is gone through, with no limit.
+*/
+
+use solitaire::certificate::*;
+use solitaire::klondike::*;
+use solitaire::misc::*;
+use solitaire::solver::*;
+
+fn usage() -> ! {
+    eprintln!("usage: certify solve DEAL [DRAW] [NODES]");
+    eprintln!("       certify check FILE...");
+    std::process::exit(1);
+}
+
+fn main() {
+    let args:Vec<String> = std::env::args().collect();
+    let arg = |i:usize, default:u64| arg_or_exit(&args, i, default);
+    match args.get(1).map(|s| s.as_str()) {
+        Some("solve") if args.len() > 2 => {
+            let rules = Rules::new(arg(3, 3) as usize, Passes::Unlimited);
+            let budget = Budget { nodes: arg(4, Budget::default().nodes), time: None };
+            match rules.certify(arg(2, 0), &budget) {
+                Some(cert) => print!("{}", cert.to_text()),
+                None => {
+                    eprintln!("out of budget, no certificate");
+                    std::process::exit(1);
+                }
+            }
+        },
+        Some("check") if args.len() > 2 => {
+            let budget = Budget { nodes: u64::MAX, time: None };
+            let mut failed = false;
+            for path in &args[2..] {
+                let result = load_certificate(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|cert| cert.verify(&budget).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => println!("{}: ok", path),
+                    Err(e) => {
+                        println!("{}: {}", path, e);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        },
+        _ => usage(),
+    }
+}
@@ -0,0 +1,56 @@
+/*----------------------------------------------------------------------
+Make a file of winnable deal numbers, proven by the solver.
+
+    dealgen COUNT [FIRST] [DRAW] [NODES] [FILE] [DIFFICULTY]
+
+Tries deal numbers from FIRST, 0 by default, until COUNT are found.
+DRAW is the draw count, 3 by default, with unlimited passes.  NODES is
+the solver budget for each deal.  The deal file goes to FILE, by
+default ./data/deals.txt.  See the dealgen module for the format.
+DIFFICULTY is easy, medium, hard or expert, by default any.  Deals are
+solved on every core, with the same results as on one.
+*/
+
+use solitaire::dealgen::*;
+use solitaire::difficulty::*;
+use solitaire::klondike::*;
+use solitaire::misc::*;
+use solitaire::solver::*;
+
+fn main() {
+    let args:Vec<String> = std::env::args().collect();
+    let arg = |i:usize, default:u64| arg_or_exit(&args, i, default);
+    if args.len() < 2 {
+        eprintln!("usage: dealgen COUNT [FIRST] [DRAW] [NODES] [FILE] [DIFFICULTY]");
+        std::process::exit(1);
+    }
+    let count = arg(1, 0) as usize;
+    let first = arg(2, 0);
+    let rules = Rules::new(arg(3, 3) as usize, Passes::Unlimited);
+    let budget = Budget { nodes: arg(4, Budget::default().nodes), time: None };
+    let path = args.get(5).cloned().unwrap_or("./data/deals.txt".to_string());
+
+    let mut gen = DealGen::new(&rules, &budget, first);
+    gen.target = args.get(6).map(|name| {
+        Difficulty::from_name(name).unwrap_or_else(|| {
+            eprintln!("difficulty is easy, medium, hard or expert, not {}", name);
+            std::process::exit(1);
+        })
+    });
+    let found = gen.take_parallel(count, 0, &mut |tried, n| {
+        eprint!("\rtried {}, found {}", tried, n);
+    });
+    eprintln!();
+    for wd in &found {
+        println!("deal {:>8}  moves {:>4}  nodes {:>8}  {:<6} {:5.1}",
+            wd.deal, wd.moves.len(), wd.stats.nodes,
+            wd.rating.difficulty.name(), wd.rating.score);
+    }
+    let deals = found.iter().map(|wd| wd.deal).collect();
+    let df = DealFile { rules, deals };
+    if let Err(e) = save_deal_file(&path, &df) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    println!("{} winnable of {} tried, written to {}", df.deals.len(), gen.tried, path);
+}
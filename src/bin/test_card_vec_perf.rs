@@ -11,6 +11,7 @@ use solitaire::misc::*;
 
 use colored::Colorize;
 
+#[allow(clippy::explicit_counter_loop, clippy::ptr_arg)]
 fn main() {
 
     const NSIZES:usize = 4;
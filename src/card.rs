@@ -90,6 +90,7 @@ fn rank_info() -> Vec<RankInfo> {
 }
 
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn test_ranks() {
     let ri = rank_info();
     for i in 0..N_RANKS {
@@ -197,6 +198,7 @@ fn suit_info() -> Vec<SuitInfo> {
 }
 
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn test_suits() {
     let vsi = suit_info();
     for i in 0..N_SUITS {
@@ -211,6 +213,53 @@ fn test_suits() {
     }
 }
 
+/*----------------------------------------------------------------------
+Look up suit and rank codes by name, ignoring case.
+"spades" -> 0,  "Queen" -> 11
+*/
+
+pub fn suit_from_name(name:&str) -> Option<u8> {
+    suit_info().iter()
+        .find(|si| si.name.to_str().eq_ignore_ascii_case(name))
+        .map(|si| si.code as u8)
+}
+
+pub fn rank_from_name(name:&str) -> Option<u8> {
+    rank_info().iter()
+        .find(|ri| ri.name.to_str().eq_ignore_ascii_case(name))
+        .map(|ri| ri.code as u8)
+}
+
+// inverse of suit_from_name and rank_from_name
+pub fn suit_name(suit:u8) -> String {
+    suit_info()[suit as usize].name.to_string()
+}
+
+pub fn rank_name(rank:u8) -> String {
+    rank_info()[rank as usize].name.to_string()
+}
+
+#[test]
+fn test_from_name() {
+    assert_eq!(suit_from_name("spades"),   Some(SuitCode::Sp as u8));
+    assert_eq!(suit_from_name("Diamonds"), Some(SuitCode::Di as u8));
+    assert_eq!(suit_from_name("CLUBS"),    Some(SuitCode::Cl as u8));
+    assert_eq!(suit_from_name("hearts"),   Some(SuitCode::He as u8));
+    assert_eq!(suit_from_name("heart"),    None);
+    assert_eq!(rank_from_name("ace"),      Some(RankCode::Ac as u8));
+    assert_eq!(rank_from_name("ten"),      Some(RankCode::NT as u8));
+    assert_eq!(rank_from_name("king"),     Some(RankCode::Ki as u8));
+    assert_eq!(rank_from_name("1"),        None);
+    assert_eq!(suit_name(SuitCode::Di as u8), "Diamonds");
+    assert_eq!(rank_name(RankCode::Qu as u8), "Queen");
+    for si in suit_info() {
+        assert_eq!(suit_name(si.code as u8), si.name.to_str());
+    }
+    for ri in rank_info() {
+        assert_eq!(rank_name(ri.code as u8), ri.name.to_str());
+    }
+}
+
 /*----------------------------------------------------------------------
 static card information
 */
@@ -267,7 +316,7 @@ FIXME
     what will be most convenient
 */
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Card {
     pub code: u8,
@@ -283,6 +332,7 @@ pub struct CardUnpacked {
 }
 
 impl Card {
+#[allow(clippy::unusual_byte_groupings, clippy::identity_op)]
 pub fn unpack(&self) -> CardUnpacked {
     CardUnpacked {
         pile    : ( self.code & 0b1_0_00_0000) != 0,
@@ -294,6 +344,7 @@ pub fn unpack(&self) -> CardUnpacked {
 }}
 
 impl Card {
+#[allow(clippy::identity_op, clippy::unnecessary_cast)]
 pub fn pack(cu:&CardUnpacked) -> Card {
     Card { code :
         0
@@ -337,11 +388,62 @@ pub fn next(&self) -> Option<Card> {
     Some ( Card { code: 0 } )
 }}
 
+/*----------------------------------------------------------------------
+Field accessors
+
+Cheaper than a full unpack when only one field is wanted.
+*/
+
+impl Card {
+pub fn rank(&self) -> u8 {
+    self.code & RANK_MASK
+}}
+
+impl Card {
+pub fn suit(&self) -> u8 {
+    (self.code & SUIT_MASK) >> 4
+}}
+
+impl Card {
+#[allow(clippy::unusual_byte_groupings)]
+pub fn face_up(&self) -> bool {
+    0 != (self.code & 0b0_1_00_0000)
+}}
+
+impl Card {
+pub fn is_ace(&self) -> bool {
+    self.rank() == RankCode::Ac as u8
+}}
+
+impl Card {
+pub fn is_king(&self) -> bool {
+    self.rank() == RankCode::Ki as u8
+}}
+
+#[test]
+#[allow(clippy::unusual_byte_groupings)]
+fn test_accessors() {
+    fn t(code:u8, suit:u8, rank:u8, up:bool) {
+        let c = Card { code };
+        assert_eq!(c.suit(), suit);
+        assert_eq!(c.rank(), rank);
+        assert_eq!(c.face_up(), up);
+    }
+    t(0b0_0_00_0000, 0,  0, false);
+    t(0b0_1_01_1100, 1, 12, true );
+    t(0b0_0_10_0111, 2,  7, false);
+    t(0b0_1_11_0001, 3,  1, true );
+    assert!( Card { code: 0b0_1_10_0000 }.is_ace());
+    assert!( Card { code: 0b0_0_01_1100 }.is_king());
+    assert!(!Card { code: 0b0_0_01_1011 }.is_king());
+}
+
 /*----------------------------------------------------------------------
 Manipulate face-up
 */
 
 impl Card {
+#[allow(clippy::unusual_byte_groupings)]
 pub fn set_face_up(&mut self, up:bool) {
     // true => 0xff, false => 0x00
     let v:u8 = misc::bool_to_allbits(up);
@@ -351,6 +453,7 @@ pub fn set_face_up(&mut self, up:bool) {
 }}
 
 #[test]
+#[allow(clippy::unusual_byte_groupings)]
 fn test_set_face_up() {
     fn t(a:u8,b:u8,up:bool) {
         let mut s = Card { code: a };
@@ -363,6 +466,70 @@ fn test_set_face_up() {
     t(0b0_1_11_0101, 0b0_1_11_0101, true  );
 }
 
+/*----------------------------------------------------------------------
+To and from text
+
+Two letters, suit first then rank, as used in the position notation.
+  suit   C H D S
+  rank   A 2 3 4 5 6 7 8 9 T J Q K
+Upper case for a face up card, lower case for face down.
+  "H3"  three of hearts, face up
+  "sq"  queen of spades, face down
+*/
+
+impl Card {
+pub fn to_text(&self) -> String {
+    let suit = suit_info()[self.suit() as usize].name.to_str().as_bytes()[0] as char;
+    let rank = rank_info()[self.rank() as usize].repr1.to_str().as_bytes()[0] as char;
+    let s = format!("{}{}", suit, rank);
+    if self.face_up() { s } else { s.to_lowercase() }
+}}
+
+// full name, "Three of Hearts"
+impl Card {
+pub fn name(&self) -> String {
+    let ri = &rank_info()[self.rank() as usize];
+    let si = &suit_info()[self.suit() as usize];
+    format!("{} of {}", ri.name, si.name)
+}}
+
+// suit code from letter, either case
+pub fn suit_from_letter(ch:char) -> Option<u8> {
+    suit_info().iter()
+        .find(|si| si.name.to_str().starts_with(ch.to_ascii_uppercase()))
+        .map(|si| si.code as u8)
+}
+
+// rank code from letter, either case
+pub fn rank_from_letter(ch:char) -> Option<u8> {
+    rank_info().iter()
+        .find(|ri| ri.repr1.to_str().starts_with(ch.to_ascii_uppercase()))
+        .map(|ri| ri.code as u8)
+}
+
+#[test]
+#[allow(clippy::unusual_byte_groupings)]
+fn test_to_text() {
+    fn t(code:u8, text:&str) {
+        let c = Card { code };
+        assert_eq!(c.to_text(), text);
+        let chars:Vec<char> = text.chars().collect();
+        assert_eq!(suit_from_letter(chars[0]), Some(c.suit()));
+        assert_eq!(rank_from_letter(chars[1]), Some(c.rank()));
+    }
+    t(0b0_1_00_0000, "SA");
+    t(0b0_0_00_0000, "sa");
+    t(0b0_1_11_0010, "H3");
+    t(0b0_0_01_1001, "dt");
+    t(0b0_1_10_1011, "CQ");
+    t(0b0_0_10_0110, "c7");
+    assert_eq!(Card { code: 0b0_1_11_0010 }.name(), "Three of Hearts");
+    assert_eq!(Card { code: 0b0_0_00_1100 }.name(), "King of Spades");
+    assert_eq!(suit_from_letter('X'), None);
+    assert_eq!(rank_from_letter('1'), None);
+    assert_eq!(rank_from_letter('0'), None);
+}
+
 /*----------------------------------------------------------------------
 To and from unicode
 */
@@ -383,6 +550,8 @@ pub fn to_unicode(&self) -> char {
 }}
 
 impl Card {
+#[allow(clippy::unnecessary_cast, clippy::manual_range_contains,
+    clippy::redundant_field_names)]
 pub fn from_unicode(c:char) -> Card {
     let vsi = &suit_info();
     let vri = &rank_info();
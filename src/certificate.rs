@@ -0,0 +1,397 @@
+/*----------------------------------------------------------------------
+Certificate module
+
+What the solver found about a deal, in a form that can be checked.
+
+    win   the winning moves from the deal.  The verifier deals the deck
+          afresh and plays them with the rules alone, no solver.  Any
+          move that is not legal, or not being won at the end, rejects
+          the certificate.  So a win checks completely.
+    lost  the number of positions that can be reached from the deal,
+          and a hash of them.  A loss cannot be shown by playing moves.
+          The verifier goes through every position reached by every
+          legal move, see closed_set below, not using the solver, and
+          finds none won.  The count and hash must match, which catches
+          a certificate edited by hand or a change in the rules.  So a
+          loss checks as far as Rules::legal_moves is right, though it
+          takes as long as finding it did, or longer.
+
+The loss is an independent proof over the closed set, not a record of
+the solver's search.  The solver prunes swapped piles and forces safe
+moves, so it looks at fewer positions, and its count is not the one in
+the certificate.  Making a lost certificate goes through the closed set
+as the verifier does, after the solver has found the loss.
+
+The text form is
+
+    solitaire-cert 1
+    rules 3 unlimited
+    deal 1
+    win D3 T4>F1 ...
+
+or instead of the win line
+
+    lost 22446 84c7928a319bff1b
+
+with the hash in hex.  Moves are as Move::to_text writes them.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use crate::deck::*;
+use crate::klondike::*;
+use crate::layout::*;
+use crate::misc::*;
+use crate::solver::*;
+
+pub const CERT_VERSION:u32 = 1;
+pub const CERT_TEXT_HEADER:&str = "solitaire-cert";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Claim {
+    Win(Vec<Move>),
+    Lost { closed:usize, hash:u64 },    // size and hash of the closed set
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    pub rules : Rules,
+    pub deal  : u64,                    // for Deck::from_deal
+    pub claim : Claim,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertError {
+    Io(String),                                 // could not read or write
+    NotCert,                                    // header missing
+    Version(u32),                               // newer than we read
+    Syntax { line:usize, text:String },         // line not understood
+    Missing(&'static str),                      // no such line
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    Illegal { index:usize, mv:Move, err:MoveError },  // move index from 0
+    NotWon,                                     // moves end short of a win
+    NotLost(Outcome),                           // search found otherwise
+    Winnable,                                   // a won position was reached
+    Mismatch { closed:usize, hash:u64 },        // what the search found
+}
+
+impl std::fmt::Display for CertError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use CertError::*;
+    match self {
+        Io(s)       => write!(f, "certificate: {}", s),
+        NotCert     => write!(f, "not a solitaire certificate"),
+        Version(v)  => write!(f, "certificate version {} is newer than {}",
+                           v, CERT_VERSION),
+        Syntax { line, text } =>
+            write!(f, "certificate line {}: cannot read \"{}\"", line, text),
+        Missing(what) => write!(f, "certificate does not give the {}", what),
+    }
+}}
+
+impl std::error::Error for CertError {}
+
+impl std::fmt::Display for VerifyError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use VerifyError::*;
+    match self {
+        Illegal { index, mv, err } =>
+            write!(f, "move {} {} is not legal: {}", index + 1, mv.to_text(), err),
+        NotWon      => write!(f, "the moves do not win"),
+        NotLost(outcome) => write!(f, "the search did not find it lost: {:?}", outcome),
+        Winnable    => write!(f, "a won position can be reached"),
+        Mismatch { closed, hash } =>
+            write!(f, "the search closed {} positions, hash {:016x}", closed, hash),
+    }
+}}
+
+impl std::error::Error for VerifyError {}
+
+/*----------------------------------------------------------------------
+Make
+*/
+
+// None when the solver did not find out, or found it lost and going
+// through every position, with the same budget, did not agree
+impl Rules {
+pub fn certify(&self, deal:u64, budget:&Budget) -> Option<Certificate> {
+    let lo = self.deal(&Deck::from_deal(deal));
+    let claim = match self.solve(&lo, 0, Mode::Thoughtful, budget).outcome {
+        Outcome::Win(moves) => Claim::Win(moves),
+        Outcome::Lost => {
+            let (closed, hash) = closed_set(self, &lo, budget).ok()?;
+            Claim::Lost { closed, hash }
+        },
+        Outcome::Unknown => return None,
+    };
+    Some(Certificate { rules: *self, deal, claim })
+}}
+
+/*----------------------------------------------------------------------
+Closed set
+
+Every position that can be reached from a start, by every legal move,
+one after another, with nothing pruned.  Each is kept by its packed
+layout and recycle count, exactly, as the solver's table is not.  With
+unlimited passes the recycle count is left out, as in the solver, since
+it does not change which moves are legal.  If none is won, the start
+is lost.  The count of positions, and FNV-1a over the sorted packed
+bytes and recycle counts, as u32 little endian, go in the certificate.
+*/
+
+fn closed_set(rules:&Rules, lo:&Layout, budget:&Budget) -> Result<(usize, u64), VerifyError> {
+    let key = |lo:&Layout, recycles:usize| {
+        let recycles = match rules.passes {
+            Passes::Unlimited => 0,
+            Passes::Limited(_) => recycles,
+        };
+        (lo.pack().expect("klondike layout packs"), recycles)
+    };
+    let start = Instant::now();
+    let mut seen:HashSet<(LayoutPacked, usize)> = HashSet::from([key(lo, 0)]);
+    let mut stack = vec![(lo.clone(), 0)];
+    let mut nodes:u64 = 0;
+    while let Some((lo, recycles)) = stack.pop() {
+        if rules.is_won(&lo) {
+            return Err(VerifyError::Winnable);
+        }
+        nodes += 1;
+        let late = nodes.is_multiple_of(1024)
+            && budget.time.is_some_and(|t| start.elapsed() >= t);
+        if nodes > budget.nodes || late {
+            return Err(VerifyError::NotLost(Outcome::Unknown));
+        }
+        for mv in rules.legal_moves(&lo, recycles) {
+            let mut next = lo.clone();
+            rules.apply(&mut next, recycles, &mv).unwrap();
+            let next_recycles = recycles + mv.is_recycle() as usize;
+            if seen.insert(key(&next, next_recycles)) {
+                stack.push((next, next_recycles));
+            }
+        }
+    }
+    let mut keys:Vec<([u8;PACK_SIZE], usize)> = seen.iter()
+        .map(|(lp, recycles)| (lp.to_bytes(), *recycles))
+        .collect();
+    keys.sort();
+    let hash = keys.iter().fold(FNV_OFFSET, |h, (bytes, recycles)| {
+        fnv1a(fnv1a(h, bytes), &(*recycles as u32).to_le_bytes())
+    });
+    Ok((keys.len(), hash))
+}
+
+/*----------------------------------------------------------------------
+Verify
+*/
+
+impl Certificate {
+pub fn verify_win(&self) -> Result<(), VerifyError> {
+    let Claim::Win(moves) = &self.claim else { return Err(VerifyError::NotWon) };
+    let mut lo = self.rules.deal(&Deck::from_deal(self.deal));
+    let mut recycles = 0;
+    for (index, mv) in moves.iter().enumerate() {
+        self.rules.apply(&mut lo, recycles, mv)
+            .map_err(|err| VerifyError::Illegal { index, mv: *mv, err })?;
+        recycles += mv.is_recycle() as usize;
+    }
+    if !self.rules.is_won(&lo) {
+        return Err(VerifyError::NotWon);
+    }
+    Ok(())
+}}
+
+// for a loss every position is gone through again, so the budget must
+// be enough for them all
+impl Certificate {
+pub fn verify(&self, budget:&Budget) -> Result<(), VerifyError> {
+    let Claim::Lost { closed, hash } = self.claim else { return self.verify_win() };
+    let lo = self.rules.deal(&Deck::from_deal(self.deal));
+    match closed_set(&self.rules, &lo, budget)? {
+        found if found == (closed, hash) => Ok(()),
+        (closed, hash) => Err(VerifyError::Mismatch { closed, hash }),
+    }
+}}
+
+/*----------------------------------------------------------------------
+Text form
+*/
+
+impl Certificate {
+pub fn to_text(&self) -> String {
+    let mut out = format!("{} {}\n", CERT_TEXT_HEADER, CERT_VERSION);
+    out += &format!("rules {}\n", self.rules.to_text());
+    out += &format!("deal {}\n", self.deal);
+    match &self.claim {
+        Claim::Win(moves) => {
+            let text:Vec<String> = moves.iter().map(|mv| mv.to_text()).collect();
+            out += &format!("win {}\n", text.join(" "));
+        },
+        Claim::Lost { closed, hash } =>
+            out += &format!("lost {} {:016x}\n", closed, hash),
+    }
+    out
+}}
+
+impl Certificate {
+pub fn from_text(text:&str) -> Result<Certificate, CertError> {
+    use CertError::*;
+    let mut lines = text.lines().enumerate()
+        .map(|(i, s)| (i + 1, s.trim()))
+        .filter(|(_, s)| !s.is_empty());
+    let version = match lines.next().map(|(_, s)| s.split_once(' ')) {
+        Some(Some((CERT_TEXT_HEADER, v))) => v.trim().parse().map_err(|_| NotCert)?,
+        _ => return Err(NotCert),
+    };
+    if version > CERT_VERSION {
+        return Err(Version(version));
+    }
+    let (mut rules, mut deal, mut claim) = (None, None, None);
+    for (line, s) in lines {
+        let syntax = || Syntax { line, text: s.to_string() };
+        let (key, rest) = s.split_once(' ').unwrap_or((s, ""));
+        match key {
+            "rules" => rules = Some(Rules::from_text(rest).ok_or_else(syntax)?),
+            "deal" => deal = Some(rest.trim().parse().map_err(|_| syntax())?),
+            "win" => claim = Some(Claim::Win(rest.split_whitespace()
+                .map(|t| Move::from_text(t).map_err(|_| syntax()))
+                .collect::<Result<_, _>>()?)),
+            "lost" => {
+                let [closed, hash] = rest.split_whitespace().collect::<Vec<_>>()[..]
+                    else { return Err(syntax()) };
+                claim = Some(Claim::Lost {
+                    closed : closed.parse().map_err(|_| syntax())?,
+                    hash   : u64::from_str_radix(hash, 16).map_err(|_| syntax())?,
+                });
+            },
+            _ => {},                    // from a later version
+        }
+    }
+    Ok(Certificate {
+        rules : rules.ok_or(Missing("rules"))?,
+        deal  : deal.ok_or(Missing("deal"))?,
+        claim : claim.ok_or(Missing("win or lost"))?,
+    })
+}}
+
+pub fn save_certificate(path:&str, cert:&Certificate) -> Result<(), CertError> {
+    std::fs::write(path, cert.to_text())
+        .map_err(|e| CertError::Io(format!("{}: {}", path, e)))
+}
+
+pub fn load_certificate(path:&str) -> Result<Certificate, CertError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| CertError::Io(format!("{}: {}", path, e)))?;
+    Certificate::from_text(&text)
+}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[test]
+fn test_certificate_win() {
+    let rules = Rules::default();
+    let budget = Budget { nodes: 2000, time: None };
+    let cert = rules.certify(1, &budget).unwrap();
+    let Claim::Win(moves) = &cert.claim else { panic!() };
+    assert_eq!(cert.verify_win(), Ok(()));
+    assert_eq!(cert.verify(&budget), Ok(()));
+    assert_eq!(Certificate::from_text(&cert.to_text()), Ok(cert.clone()));
+
+    // a move changed, or one short
+    let mut bad = cert.clone();
+    let mut changed = moves.clone();
+    changed[0] = Move { from: TABLEAU, to: TABLEAU + 1, n: 3 };
+    bad.claim = Claim::Win(changed);
+    assert!(matches!(bad.verify_win(), Err(VerifyError::Illegal { index: 0, .. })));
+    bad.claim = Claim::Win(moves[..moves.len() - 1].to_vec());
+    assert_eq!(bad.verify_win(), Err(VerifyError::NotWon));
+    // another deal
+    assert!(Certificate { deal: 2, ..cert.clone() }.verify_win().is_err());
+    // out of budget, nothing to certify
+    assert_eq!(rules.certify(3, &budget), None);
+}
+
+#[test]
+fn test_certificate_lost() {
+    // deal 4 cannot be won, see autoplay
+    let rules = Rules::default();
+    let budget = Budget { nodes: 50_000, time: None };
+    let cert = rules.certify(4, &budget).unwrap();
+    let Claim::Lost { closed, hash } = cert.claim else { panic!() };
+    assert_eq!(cert.verify(&budget), Ok(()));
+    let text = cert.to_text();
+    assert!(text.ends_with(&format!("\nlost {} {:016x}\n", closed, hash)));
+    assert_eq!(Certificate::from_text(&text), Ok(cert.clone()));
+
+    let edited = Certificate { claim: Claim::Lost { closed, hash: hash ^ 1 }, ..cert };
+    assert_eq!(edited.verify(&budget), Err(VerifyError::Mismatch { closed, hash }));
+    assert_eq!(edited.verify(&Budget { nodes: 10, time: None }),
+        Err(VerifyError::NotLost(Outcome::Unknown)));
+    assert_eq!(edited.verify_win(), Err(VerifyError::NotWon));
+}
+
+#[test]
+fn test_closed_set() {
+    let rules = Rules::default();
+    let budget = Budget::default();
+    // a card is missing, so nothing is won however it goes
+    let lo = Layout::from_text(
+        "P4c3h5d9s2 P0 P1SA P0P0P0 P1S3 P2h2C7 P0P0P0P0P1D8").unwrap();
+    let (positions, hash) = closed_set(&rules, &lo, &budget).unwrap();
+    assert!(positions > 10);
+    // the same start, the same set, and piles swapped is another start
+    assert_eq!(closed_set(&rules, &lo, &budget), Ok((positions, hash)));
+    let mut order:Vec<usize> = (0..N_PILES).collect();
+    order.swap(TABLEAU, TABLEAU + 5);
+    let (_, other) = closed_set(&rules, &lo.permuted(&order), &budget).unwrap();
+    assert_ne!(other, hash);
+    assert_eq!(closed_set(&rules, &lo, &Budget { nodes: 5, time: None }),
+        Err(VerifyError::NotLost(Outcome::Unknown)));
+
+    // kings buried under their queens, which can be won
+    let lo = Layout::from_text(
+        "P1hq P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2skSQ P2hkCQ P2ckDQ P1DK P0P0P0").unwrap();
+    assert_eq!(closed_set(&rules, &lo, &budget), Err(VerifyError::Winnable));
+}
+
+#[test]
+fn test_certificate_text() {
+    use CertError::*;
+    let text = "solitaire-cert 1\nrules 1 3\ndeal 7\nwin D1 W>F1 R1\n";
+    let cert = Certificate::from_text(text).unwrap();
+    assert_eq!(cert.rules, Rules::draw1());
+    assert_eq!(cert.to_text(), text);
+    let t = |s:&str| Certificate::from_text(s).unwrap_err();
+    assert_eq!(t(""), NotCert);
+    assert_eq!(t("solitaire-cert 2\n"), Version(2));
+    assert_eq!(t("solitaire-cert 1\nrules 1 3\nwin\n"), Missing("deal"));
+    assert_eq!(t("solitaire-cert 1\nrules 1 3\ndeal 7\n"), Missing("win or lost"));
+    assert_eq!(t(&text.replace("W>F1", "W>F9")),
+        Syntax { line: 4, text: "win D1 W>F9 R1".to_string() });
+    assert_eq!(t(&text.replace("win D1 W>F1 R1", "lost 12 xyz")),
+        Syntax { line: 4, text: "lost 12 xyz".to_string() });
+
+    let path = std::env::temp_dir().join("solitaire_test_cert.txt");
+    let path = path.to_str().unwrap();
+    save_certificate(path, &cert).unwrap();
+    assert_eq!(load_certificate(path), Ok(cert));
+    std::fs::remove_file(path).unwrap();
+}
+
+// end mod certificate
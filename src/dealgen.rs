@@ -0,0 +1,297 @@
+/*----------------------------------------------------------------------
+Dealgen module
+
+Only winnable deals.  Deal numbers are tried in turn, each shuffled
+with Deck::from_deal, dealt, and given to the solver in thoughtful mode
+with a budget.  Only a deal the solver wins is given out, with the
+winning line.  A deal the solver finds lost, or runs out of budget on,
+is passed over.  So a smaller budget passes over some winnable deals,
+but never gives out a deal that cannot be won.
+
+Each deal won is rated, see the difficulty module.  With a target set,
+only deals of that difficulty are given out.
+
+take_parallel solves deals a block at a time on several threads, see
+Rules::solve_many.  It gives the same deals as the iterator would, and
+leaves next and tried the same, whatever the number of threads.
+
+Searching takes time, so deal files hold lists of deal numbers already
+proven winnable, for use offline.  The text form is
+
+    solitaire-deals 1
+    rules 3 unlimited
+    # anything after a # is a comment
+    0
+    1
+    2
+
+The deal numbers only mean anything with the same shuffle and rules.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use crate::deck::*;
+use crate::difficulty::*;
+use crate::klondike::*;
+use crate::layout::*;
+use crate::solver::*;
+
+pub const DEALS_VERSION:u32 = 1;
+pub const DEALS_TEXT_HEADER:&str = "solitaire-deals";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WinnableDeal {
+    pub deal   : u64,                   // for Deck::from_deal
+    pub moves  : Vec<Move>,             // a winning line from the deal
+    pub stats  : Stats,
+    pub rating : Rating,
+}
+
+#[derive(Clone, Debug)]
+pub struct DealGen {
+    pub rules  : Rules,
+    pub budget : Budget,
+    pub target : Option<Difficulty>,    // None takes any
+    pub next   : u64,                   // next deal number to try
+    pub tried  : u64,                   // deals tried so far
+}
+
+impl DealGen {
+pub fn new(rules:&Rules, budget:&Budget, first:u64) -> DealGen {
+    DealGen { rules: *rules, budget: *budget, target: None, next: first, tried: 0 }
+}}
+
+// deals solved at a time by take_parallel, for each thread
+pub const DEALS_PER_THREAD:u64 = 4;
+
+// solve one deal, None if not proven winnable or not the target
+impl DealGen {
+pub fn try_deal(&self, deal:u64) -> Option<WinnableDeal> {
+    let lo = self.rules.deal(&Deck::from_deal(deal));
+    let sol = self.rules.solve(&lo, 0, Mode::Thoughtful, &self.budget);
+    self.judge(deal, &lo, sol)
+}}
+
+impl DealGen {
+fn judge(&self, deal:u64, lo:&Layout, sol:Solution) -> Option<WinnableDeal> {
+    let Outcome::Win(moves) = sol.outcome else { return None };
+    let rating = self.rules.rate(lo, &moves, &sol.stats);
+    if self.target.is_some_and(|d| d != rating.difficulty) {
+        return None;
+    }
+    Some(WinnableDeal { deal, moves, stats: sol.stats, rating })
+}}
+
+// never ends, unless every deal number has been tried
+impl Iterator for DealGen {
+type Item = WinnableDeal;
+fn next(&mut self) -> Option<WinnableDeal> {
+    loop {
+        let deal = self.next;
+        self.next = self.next.checked_add(1)?;
+        self.tried += 1;
+        if let Some(wd) = self.try_deal(deal) {
+            return Some(wd);
+        }
+    }
+}}
+
+// the next count deals, solving on threads, 0 for every core.
+// progress is given deals tried and found after each block.
+impl DealGen {
+pub fn take_parallel(&mut self, count:usize, threads:usize,
+    progress:&mut dyn FnMut(u64, usize)) -> Vec<WinnableDeal>
+{
+    let block = DEALS_PER_THREAD * thread_count(threads) as u64;
+    let mut found = vec![];
+    while found.len() < count {
+        let deals:Vec<u64> = (self.next..self.next.saturating_add(block)).collect();
+        if deals.is_empty() {
+            break;
+        }
+        let positions:Vec<(Layout, usize)> = deals.iter()
+            .map(|&deal| (self.rules.deal(&Deck::from_deal(deal)), 0))
+            .collect();
+        let sols = self.rules.solve_many(&positions, Mode::Thoughtful, &self.budget,
+            threads, &mut |_, _| {});
+        for ((deal, (lo, _)), sol) in deals.into_iter().zip(positions).zip(sols) {
+            self.next = deal + 1;
+            self.tried += 1;
+            if let Some(wd) = self.judge(deal, &lo, sol) {
+                found.push(wd);
+                if found.len() == count {
+                    break;
+                }
+            }
+        }
+        progress(self.tried, found.len());
+    }
+    found
+}}
+
+/*----------------------------------------------------------------------
+Deal files
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DealFile {
+    pub rules : Rules,
+    pub deals : Vec<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DealFileError {
+    Io(String),                                 // could not read or write
+    NotDeals,                                   // header missing
+    Version(u32),                               // newer than we read
+    Syntax { line:usize, text:String },         // line not understood
+    NoRules,
+}
+
+impl std::fmt::Display for DealFileError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use DealFileError::*;
+    match self {
+        Io(s)       => write!(f, "deal file: {}", s),
+        NotDeals    => write!(f, "not a solitaire deal file"),
+        Version(v)  => write!(f, "deal file version {} is newer than {}",
+                           v, DEALS_VERSION),
+        Syntax { line, text } =>
+            write!(f, "deal file line {}: cannot read \"{}\"", line, text),
+        NoRules     => write!(f, "deal file does not give the rules"),
+    }
+}}
+
+impl std::error::Error for DealFileError {}
+
+impl DealFile {
+pub fn to_text(&self) -> String {
+    let mut out = format!("{} {}\n", DEALS_TEXT_HEADER, DEALS_VERSION);
+    out += &format!("rules {}\n", self.rules.to_text());
+    for deal in &self.deals {
+        out += &format!("{}\n", deal);
+    }
+    out
+}}
+
+impl DealFile {
+pub fn from_text(text:&str) -> Result<DealFile, DealFileError> {
+    use DealFileError::*;
+    let mut lines = text.lines().enumerate()
+        .map(|(i, s)| (i + 1, s.split('#').next().unwrap().trim()))
+        .filter(|(_, s)| !s.is_empty());
+    let version = match lines.next().map(|(_, s)| s.split_once(' ')) {
+        Some(Some((DEALS_TEXT_HEADER, v))) => v.trim().parse().map_err(|_| NotDeals)?,
+        _ => return Err(NotDeals),
+    };
+    if version > DEALS_VERSION {
+        return Err(Version(version));
+    }
+    let mut rules = None;
+    let mut deals = vec![];
+    for (line, s) in lines {
+        let syntax = || Syntax { line, text: s.to_string() };
+        if let Some(rest) = s.strip_prefix("rules ") {
+            rules = Some(Rules::from_text(rest).ok_or_else(syntax)?);
+        } else {
+            deals.push(s.parse().map_err(|_| syntax())?);
+        }
+    }
+    Ok(DealFile { rules: rules.ok_or(NoRules)?, deals })
+}}
+
+pub fn save_deal_file(path:&str, df:&DealFile) -> Result<(), DealFileError> {
+    std::fs::write(path, df.to_text())
+        .map_err(|e| DealFileError::Io(format!("{}: {}", path, e)))
+}
+
+pub fn load_deal_file(path:&str) -> Result<DealFile, DealFileError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| DealFileError::Io(format!("{}: {}", path, e)))?;
+    DealFile::from_text(&text)
+}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[test]
+fn test_dealgen() {
+    let rules = Rules::default();
+    let budget = Budget { nodes: 2000, time: None };
+    let found:Vec<WinnableDeal> = DealGen::new(&rules, &budget, 0).take(4).collect();
+    let deals:Vec<u64> = found.iter().map(|wd| wd.deal).collect();
+    // deals 3 to 7 are lost or need more than the budget
+    assert_eq!(deals, [0, 1, 2, 8]);
+    for wd in &found {
+        let mut lo = rules.deal(&Deck::from_deal(wd.deal));
+        let mut recycles = 0;
+        for mv in &wd.moves {
+            rules.apply(&mut lo, recycles, mv).unwrap();
+            recycles += mv.is_recycle() as usize;
+        }
+        assert!(rules.is_won(&lo));
+    }
+    let mut gen = DealGen::new(&rules, &budget, 3);
+    assert_eq!(gen.next().map(|wd| wd.deal), Some(8));
+    assert_eq!((gen.next, gen.tried), (9, 6));
+
+    // with a target, the first of those with that difficulty
+    for wd in &found {
+        let mut gen = DealGen::new(&rules, &budget, 0);
+        gen.target = Some(wd.rating.difficulty);
+        let first = found.iter().find(|f| f.rating.difficulty == wd.rating.difficulty);
+        let got = gen.next().unwrap();
+        assert_eq!((got.deal, got.rating), (first.unwrap().deal, first.unwrap().rating));
+    }
+
+    // the same on threads, and ready to go on from there
+    for (threads, blocks) in [(1, 2), (3, 1)] {
+        let mut gen = DealGen::new(&rules, &budget, 3);
+        let mut calls = vec![];
+        let got = gen.take_parallel(1, threads, &mut |tried, n| calls.push((tried, n)));
+        assert_eq!((got[0].deal, got[0].rating), (found[3].deal, found[3].rating));
+        assert_eq!((gen.next, gen.tried), (9, 6));
+        assert_eq!((calls.len(), calls.last()), (blocks, Some(&(6, 1))));
+    }
+    let mut gen = DealGen::new(&rules, &budget, 0);
+    let got = gen.take_parallel(4, 0, &mut |_, _| {});
+    assert_eq!(got.iter().map(|wd| wd.deal).collect::<Vec<_>>(), deals);
+    let mut gen = DealGen::new(&rules, &budget, u64::MAX - 2);
+    gen.take_parallel(1, 0, &mut |_, _| {});
+    assert_eq!(gen.next, u64::MAX);
+}
+
+#[test]
+fn test_deal_file() {
+    let df = DealFile { rules: Rules::draw1(), deals: vec![0, 1, 2, 8, 1 << 40] };
+    let text = df.to_text();
+    assert_eq!(text, "solitaire-deals 1\nrules 1 3\n0\n1\n2\n8\n1099511627776\n");
+    assert_eq!(DealFile::from_text(&text), Ok(df.clone()));
+    let commented = "# made for testing\n".to_string()
+        + &text.replace("\n2\n", "\n\n  2  # the third\n");
+    assert_eq!(DealFile::from_text(&commented), Ok(df.clone()));
+
+    use DealFileError::*;
+    let t = |s:&str| DealFile::from_text(s).unwrap_err();
+    assert_eq!(t(""), NotDeals);
+    assert_eq!(t("0\n1\n"), NotDeals);
+    assert_eq!(t("solitaire-deals 2\n"), Version(2));
+    assert_eq!(t("solitaire-deals 1\n7\n"), NoRules);
+    assert_eq!(t("solitaire-deals 1\nrules 3\n"),
+        Syntax { line:2, text:"rules 3".to_string() });
+    assert_eq!(t(&text.replace("\n8\n", "\n-8\n")),
+        Syntax { line:6, text:"-8".to_string() });
+
+    let path = std::env::temp_dir().join("solitaire_test_deals.txt");
+    let path = path.to_str().unwrap();
+    save_deal_file(path, &df).unwrap();
+    assert_eq!(load_deal_file(path), Ok(df));
+    std::fs::remove_file(path).unwrap();
+}
+
+// end mod dealgen
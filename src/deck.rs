@@ -28,6 +28,7 @@ pub struct Selectors {
 
 // new standard deck in canonical order
 impl Deck {
+#[allow(clippy::new_without_default)]
 pub fn new() -> Deck {
     let ci = Card::info();
     let mut cards:Vec<u8> = vec![];
@@ -49,7 +50,7 @@ fn rand_selectors() -> Selectors {
 
 // test deck for validity
 impl Deck {
-fn valid(&self) -> bool {
+pub fn valid(&self) -> bool {
     // get new reference deck and copy of self for testing
     let dref = Deck::new();
     let mut dtest:Deck = (*self).clone();
@@ -75,6 +76,7 @@ functions.
 */
 
 impl Deck {
+#[allow(clippy::len_zero)]
 pub fn shuffle(&mut self, mut vsels:Vec<Selectors>, nrounds:usize) {
     let n_cards2 = Card::info().n_cards/2;
     for _ in 0..nrounds {
@@ -140,6 +142,161 @@ fn test_shuffle() {
     //println!("Shuffles/second rate: {}", rate);
 }
 
+/*----------------------------------------------------------------------
+Deal numbers
+
+A deal number names one shuffled deck.  The same number gives the same
+deck on every platform and build, so it can be shown on screen, kept in
+a save file or quoted in a bug report.
+
+The deal number seeds a SplitMix64 generator, which supplies the
+selectors for DEAL_ROUNDS rounds of the shuffle above, starting from a
+new deck in canonical order.  Changing any of this changes every deal,
+so the test below pins down a few of them.
+*/
+
+pub const DEAL_ROUNDS:usize = 10;
+
+impl Deck {
+pub fn from_deal(deal:u64) -> Deck {
+    let n_cards = Card::info().n_cards;
+    let mut rng = SplitMix64::new(deal);
+    let mut vsels:Vec<Selectors> = vec![];
+    for _ in 0..DEAL_ROUNDS {
+        let sels = (0..n_cards).map(|_| (rng.next_u64() >> 63) as u8).collect();
+        vsels.push(Selectors { sels });
+    }
+    let mut deck = Deck::new();
+    deck.shuffle(vsels, DEAL_ROUNDS);
+    deck
+}}
+
+#[test]
+fn test_from_deal() {
+    // same number, same deck
+    for deal in [0, 1, 2, 11982, u64::MAX] {
+        let d = Deck::from_deal(deal);
+        assert!(d.valid());
+        assert_eq!(d, Deck::from_deal(deal));
+        assert_ne!(d, Deck::new());
+    }
+    assert_ne!(Deck::from_deal(1), Deck::from_deal(2));
+
+    // pin down the first few cards of some deals
+    fn t(deal:u64, head:&[u8]) {
+        assert_eq!(&Deck::from_deal(deal).cards[..head.len()], head);
+    }
+    t(0,     &[0x1c, 0x0b, 0x37, 0x1a, 0x1b, 0x18, 0x2a, 0x13]);
+    t(1,     &[0x09, 0x27, 0x3c, 0x37, 0x24, 0x14, 0x08, 0x07]);
+    t(11982, &[0x0c, 0x04, 0x23, 0x03, 0x3c, 0x34, 0x10, 0x24]);
+}
+
+/*----------------------------------------------------------------------
+Microsoft deal numbers
+
+Reproduce the decks of Microsoft FreeCell, so that famous deals such as
+game #11982 can be imported and checked against existing deal databases.
+
+The generator is the linear congruential rand() of the Microsoft C
+library.  Each deal number is the seed.  Cards are numbered 0..52 as
+4*rank + suit, with suits in the order clubs, diamonds, hearts, spades.
+Each step picks one of the remaining cards at random, and the last
+remaining card takes its place.
+
+    seed = seed * 214013 + 2531011
+    rand = (seed >> 16) & 0x7fff
+
+Deals 1..=32000 are the original game.  The extended range of later
+versions and of freecell solver goes up to 2^33-1:
+
+    below 2^31       rand as above
+    2^31 .. 2^32     rand as above, with bit 15 set
+    2^32 .. 2^33     seed is deal - 2^32,  rand = ((seed>>16) & 0xffff) + 1
+
+The resulting deck lists cards in the order FreeCell deals them, left to
+right across the 8 columns, row by row.  See:
+https://rosettacode.org/wiki/Deal_cards_for_FreeCell
+https://fc-solve.shlomifish.org/
+*/
+
+pub const MS_DEAL_MAX:u64 = (1<<33) - 1;
+
+impl Deck {
+pub fn from_ms_deal(deal:u64) -> Option<Deck> {
+    if deal == 0 || deal > MS_DEAL_MAX {
+        return None;
+    }
+    let extended = deal >= 1<<32;
+    let mut seed:u64 = if extended { deal - (1<<32) } else { deal };
+    let mut rand = || -> u64 {
+        seed = seed.wrapping_mul(214013).wrapping_add(2531011);
+        if extended {
+            ((seed >> 16) & 0xffff) + 1
+        } else if 0 != (deal & 0x8000_0000) {
+            ((seed >> 16) & 0x7fff) | 0x8000
+        } else {
+            (seed >> 16) & 0x7fff
+        }
+    };
+    // microsoft suit order is clubs, diamonds, hearts, spades
+    const SUITS:[u8;4] = [2, 1, 3, 0];
+    let n_cards = Card::info().n_cards;
+    let mut left:Vec<u8> = (0..n_cards as u8).collect();
+    let mut cards:Vec<u8> = vec![];
+    for n in (1..=n_cards).rev() {
+        let j = (rand() % n as u64) as usize;
+        let ms = left[j];
+        left[j] = left[n-1];
+        cards.push((SUITS[(ms % 4) as usize] << 4) | (ms / 4));
+    }
+    Some(Deck { cards })
+}}
+
+#[test]
+fn test_from_ms_deal() {
+    // card names like "JD" to deck codes
+    fn code(name:&str) -> u8 {
+        let b = name.as_bytes();
+        let rank = "A23456789TJQK".find(b[0] as char).unwrap() as u8;
+        let suit = "SDCH".find(b[1] as char).unwrap() as u8;
+        (suit << 4) | rank
+    }
+    fn t(deal:u64, listing:&str) {
+        let want:Vec<u8> = listing.split_whitespace().map(code).collect();
+        let deck = Deck::from_ms_deal(deal).unwrap();
+        assert!(deck.valid());
+        assert_eq!(deck.cards, want);
+    }
+    // published listings, 8 columns dealt row by row
+    t(1, "
+        JD 2D 9H JC 5D 7H 7C 5H
+        KD KC 9S 5S AD QC KH 3H
+        2S KS 9D QD JS AS AH 3C
+        4C 5C TS QH 4H AC 4D 7S
+        3S TD 4S TH 8H 2C JH 7D
+        6D 8S 8D QS 6C 3D 8C TC
+        6S 9C 2H 6H
+    ");
+    t(617, "
+        7D AD 5C 3S 5S 8C 2D AH
+        TD 7S QD AC 6D 8H AS KH
+        TH QC 3H 9D 6S 8D 3D TC
+        KD 5H 9S 3C 8S 7H 4D JS
+        4C QS 9C 9H 7C 6H 2C 2S
+        4S TS 2H 5D JC 6C JH QH
+        JD KS KC 4H
+    ");
+
+    // extended range gives valid, distinct decks
+    for deal in [11982, 32000, 1<<31, (1<<32) - 1, 1<<32, MS_DEAL_MAX] {
+        let d = Deck::from_ms_deal(deal).unwrap();
+        assert!(d.valid());
+        assert_ne!(Some(d), Deck::from_ms_deal(deal - 1));
+    }
+    assert_eq!(Deck::from_ms_deal(0), None);
+    assert_eq!(Deck::from_ms_deal(MS_DEAL_MAX + 1), None);
+}
+
 /*----------------------------------------------------------------------
 Test if deck is random based on number of runs
 A run is a sequence of values that increase or decrease
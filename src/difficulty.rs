@@ -0,0 +1,196 @@
+/*----------------------------------------------------------------------
+Difficulty module
+
+Selective difficulty.  A deal the solver can win is given a score from
+how hard the solver found it, and the score is put in one of four
+buckets, easy, medium, hard and expert.
+
+What goes into the score:
+    nodes       positions the solver searched
+    dead ends   moves the solver tried that did not lead to the win
+    length      moves in the winning line
+    passes      times the waste is turned over in the winning line
+    blind       face down cards turned up in the winning line before
+                the first card other than an ace goes to a foundation
+
+Searching is counted on a log scale, since it spans from a hundred
+positions to a million.  The weights and bucket limits were set by
+hand, for draw three deals and a budget of 200,000 positions, and mean
+little for other rules or budgets.  test_buckets, ignored since it
+takes minutes, rates the first hundred deals that way and prints how
+many land in each bucket.
+
+A deal the solver cannot win, lost or out of budget, gets no rating.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use crate::deck::*;
+use crate::klondike::*;
+use crate::layout::*;
+use crate::solver::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+// lowest score in each bucket
+const MEDIUM:f64 = 40.0;
+const HARD:f64 = 55.0;
+const EXPERT:f64 = 70.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    pub score      : f64,
+    pub difficulty : Difficulty,
+    pub nodes      : u64,
+    pub dead_ends  : u64,
+    pub length     : usize,
+    pub passes     : usize,
+    pub blind      : usize,
+}
+
+impl Difficulty {
+pub fn from_score(score:f64) -> Difficulty {
+    match score {
+        s if s < MEDIUM => Difficulty::Easy,
+        s if s < HARD   => Difficulty::Medium,
+        s if s < EXPERT => Difficulty::Hard,
+        _               => Difficulty::Expert,
+    }
+}}
+
+impl Difficulty {
+pub fn name(&self) -> &'static str {
+    match self {
+        Difficulty::Easy   => "easy",
+        Difficulty::Medium => "medium",
+        Difficulty::Hard   => "hard",
+        Difficulty::Expert => "expert",
+    }
+}}
+
+impl Difficulty {
+pub fn from_name(name:&str) -> Option<Difficulty> {
+    [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert]
+        .into_iter()
+        .find(|d| d.name().eq_ignore_ascii_case(name))
+}}
+
+/*----------------------------------------------------------------------
+Rate
+*/
+
+// replay the winning line from lo, gathering what the score needs
+impl Rules {
+pub fn rate(&self, lo:&Layout, moves:&[Move], stats:&Stats) -> Rating {
+    let mut lo = lo.clone();
+    let mut recycles = 0;
+    let mut blind = 0;
+    let mut progress = false;
+    for mv in moves {
+        let src = lo.pile(mv.from);
+        if let Role::Foundation(_) = role(mv.to) {
+            progress |= !src.last().unwrap().is_ace();
+        }
+        if !progress && matches!(role(mv.from), Role::Tableau(_)) && turns_up(&lo, mv, recycles) {
+            blind += 1;
+        }
+        self.apply(&mut lo, recycles, mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+    }
+    let log = |x:u64| (1.0 + x as f64).log2();
+    let score = 2.0 * log(stats.nodes)
+        + 1.0 * log(stats.dead_ends)
+        + 0.05 * moves.len() as f64
+        + 1.0 * recycles as f64
+        + 1.0 * blind as f64;
+    Rating {
+        score,
+        difficulty : Difficulty::from_score(score),
+        nodes      : stats.nodes,
+        dead_ends  : stats.dead_ends,
+        length     : moves.len(),
+        passes     : recycles,
+        blind,
+    }
+}}
+
+// solve and rate a deal, None if not won within the budget
+impl Rules {
+pub fn rate_deal(&self, deal:u64, budget:&Budget) -> Option<Rating> {
+    let lo = self.deal(&Deck::from_deal(deal));
+    let sol = self.solve(&lo, 0, Mode::Thoughtful, budget);
+    match &sol.outcome {
+        Outcome::Win(moves) => Some(self.rate(&lo, moves, &sol.stats)),
+        _ => None,
+    }
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[test]
+fn test_difficulty() {
+    use Difficulty::*;
+    assert_eq!(Difficulty::from_score(0.0), Easy);
+    assert_eq!(Difficulty::from_score(MEDIUM), Medium);
+    assert_eq!(Difficulty::from_score(HARD - 0.1), Medium);
+    assert_eq!(Difficulty::from_score(EXPERT + 100.0), Expert);
+    for d in [Easy, Medium, Hard, Expert] {
+        assert_eq!(Difficulty::from_name(d.name()), Some(d));
+    }
+    assert_eq!(Difficulty::from_name("HARD"), Some(Hard));
+    assert_eq!(Difficulty::from_name("impossible"), None);
+    assert!(Easy < Expert);
+}
+
+#[test]
+fn test_rate() {
+    let rules = Rules::default();
+    let budget = Budget { nodes: 2000, time: None };
+    // found straight away
+    let r = rules.rate_deal(1, &budget).unwrap();
+    assert_eq!(r.difficulty, Difficulty::Easy);
+    assert_eq!(r.nodes, r.length as u64);
+    // not won within the budget
+    assert_eq!(rules.rate_deal(3, &budget), None);
+
+    // more search, more turning over, more blind moves: harder
+    let stats = Stats { nodes: 100_000, dead_ends: 150_000, ..Default::default() };
+    let lo = rules.deal(&Deck::from_deal(1));
+    let Outcome::Win(moves) = rules.solve(&lo, 0, Mode::Thoughtful, &budget).outcome
+        else { panic!() };
+    let harder = rules.rate(&lo, &moves, &stats);
+    assert!(harder.score > r.score);
+    assert_eq!((harder.passes, harder.blind), (r.passes, r.blind));
+}
+
+// cargo test --release test_buckets -- --ignored --nocapture
+#[test]
+#[ignore]
+fn test_buckets() {
+    let rules = Rules::default();
+    let budget = Budget { nodes: 200_000, time: None };
+    let mut counts = [0usize;4];
+    for deal in 0..100 {
+        if let Some(r) = rules.rate_deal(deal, &budget) {
+            counts[r.difficulty as usize] += 1;
+        }
+    }
+    println!("easy {} medium {} hard {} expert {}",
+        counts[0], counts[1], counts[2], counts[3]);
+    // every bucket gets some
+    assert!(counts.iter().all(|n| *n > 0));
+}
+
+// end mod difficulty
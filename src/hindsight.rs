@@ -0,0 +1,248 @@
+/*----------------------------------------------------------------------
+Hindsight module
+
+Odds of winning when the face down cards are not known.  The cards the
+player cannot have seen are shuffled among the places they could be,
+many times, each giving a sample layout that agrees with all the player
+knows.  Each sample is solved in thoughtful mode, knowing every card.
+The share of samples won estimates the odds, for the position and for
+each move from it.  This is hindsight optimisation, after Yan,
+Diaconis, Rusmevichientong and Van Roy, "Solitaire: Man Versus
+Machine", 2005.  It favours a move that wins in many of the ways the
+hidden cards could lie.
+
+Which cards are hidden:
+    tableau  every face down card
+    stock    every card before the first recycle.  A recycle is only
+             allowed when the stock is empty, so by then each card in
+             it has been seen in the waste, in an order that does not
+             change.
+
+For each move the sample is played on with that move and solved from
+there, so the cost is samples times moves times a search.  The moves
+tried are those the solver would try, less the forcing of safe moves.
+Searches are spread over threads, see Rules::solve_many, and with a
+node budget the odds are the same whatever the number of threads.
+
+A sample the solver runs out of budget on is counted as not won.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use crate::card::*;
+use crate::klondike::*;
+use crate::layout::*;
+use crate::misc::*;
+use crate::solver::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sampling {
+    pub samples : usize,
+    pub seed    : u64,
+    pub budget  : Budget,               // for each search
+    pub threads : usize,                // 0 for every core
+}
+
+impl Default for Sampling {
+    fn default() -> Sampling {
+        Sampling {
+            samples : 20,
+            seed    : 0,
+            budget  : Budget { nodes: 20_000, time: None },
+            threads : 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Odds {
+    pub mv      : Move,
+    pub won     : usize,
+    pub lost    : usize,
+    pub unknown : usize,                // out of budget
+}
+
+impl Odds {
+pub fn samples(&self) -> usize {
+    self.won + self.lost + self.unknown
+}}
+
+impl Odds {
+pub fn p(&self) -> f64 {
+    self.won as f64 / self.samples().max(1) as f64
+}}
+
+/*----------------------------------------------------------------------
+Samples
+*/
+
+// places of the cards the player cannot have seen, as (pile, index)
+impl Rules {
+pub fn hidden_places(&self, lo:&Layout, recycles:usize) -> Vec<(usize, usize)> {
+    let mut places = vec![];
+    if 0 == recycles {
+        places.extend((0..lo.pile(STOCK).len()).map(|i| (STOCK, i)));
+    }
+    for t in TABLEAU..N_PILES {
+        let p = lo.pile(t);
+        places.extend((0..p.len()).filter(|&i| !p[i].face_up()).map(|i| (t, i)));
+    }
+    places
+}}
+
+// the hidden cards shuffled among their places
+impl Rules {
+pub fn sample_hidden(&self, lo:&Layout, recycles:usize, rng:&mut SplitMix64) -> Layout {
+    let places = self.hidden_places(lo, recycles);
+    let mut cards:Vec<Card> = places.iter().map(|&(p, i)| lo.pile(p)[i]).collect();
+    for i in (1..cards.len()).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        cards.swap(i, j);
+    }
+    let mut piles = lo.piles().to_vec();
+    for (&(p, i), card) in places.iter().zip(cards) {
+        piles[p][i] = card;
+    }
+    Layout::new(piles)
+}}
+
+impl Rules {
+fn samples(&self, lo:&Layout, recycles:usize, s:&Sampling) -> Vec<Layout> {
+    let mut rng = SplitMix64::new(s.seed);
+    (0..s.samples).map(|_| self.sample_hidden(lo, recycles, &mut rng)).collect()
+}}
+
+/*----------------------------------------------------------------------
+Estimates
+*/
+
+// share of samples won from the position
+impl Rules {
+pub fn win_odds(&self, lo:&Layout, recycles:usize, s:&Sampling) -> f64 {
+    let positions:Vec<(Layout, usize)> = self.samples(lo, recycles, s).into_iter()
+        .map(|sample| (sample, recycles))
+        .collect();
+    let sols = self.solve_many(&positions, Mode::Thoughtful, &s.budget, s.threads,
+        &mut |_, _| {});
+    let won = sols.iter().filter(|sol| matches!(sol.outcome, Outcome::Win(_))).count();
+    won as f64 / s.samples.max(1) as f64
+}}
+
+// odds for each of the moves given, in the same order.  The same
+// samples are used for every move.
+impl Rules {
+pub fn estimate_moves(&self, lo:&Layout, recycles:usize, moves:&[Move], s:&Sampling)
+    -> Vec<Odds>
+{
+    let mut positions = vec![];
+    for sample in self.samples(lo, recycles, s) {
+        for mv in moves {
+            let mut next = sample.clone();
+            self.apply(&mut next, recycles, mv).expect("move is legal");
+            positions.push((next, recycles + mv.is_recycle() as usize));
+        }
+    }
+    let sols = self.solve_many(&positions, Mode::Thoughtful, &s.budget, s.threads,
+        &mut |_, _| {});
+    let mut odds:Vec<Odds> = moves.iter()
+        .map(|&mv| Odds { mv, won: 0, lost: 0, unknown: 0 })
+        .collect();
+    for (i, sol) in sols.iter().enumerate() {
+        let o = &mut odds[i % moves.len()];
+        match sol.outcome {
+            Outcome::Win(_) => o.won += 1,
+            Outcome::Lost => o.lost += 1,
+            Outcome::Unknown => o.unknown += 1,
+        }
+    }
+    odds
+}}
+
+// odds for the moves worth trying, best first
+impl Rules {
+pub fn estimate(&self, lo:&Layout, recycles:usize, s:&Sampling) -> Vec<Odds> {
+    let moves:Vec<Move> = self.legal_moves(lo, recycles).into_iter()
+        .filter(|mv| score(lo, mv).is_some())
+        .collect();
+    let mut odds = self.estimate_moves(lo, recycles, &moves, s);
+    odds.sort_by_key(|o| std::cmp::Reverse(o.won));
+    odds
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[test]
+fn test_sample_hidden() {
+    use crate::deck::*;
+    let rules = Rules::default();
+    let lo = rules.deal(&Deck::from_deal(5));
+    // 24 in the stock, 21 face down in the tableau
+    assert_eq!(rules.hidden_places(&lo, 0).len(), 45);
+    let mut rng = SplitMix64::new(1);
+    let a = rules.sample_hidden(&lo, 0, &mut rng);
+    let b = rules.sample_hidden(&lo, 0, &mut rng);
+    assert_ne!(a, b);
+    assert_ne!(a, lo);
+    let codes = |lo:&Layout| {
+        let mut v:Vec<u8> = lo.piles().concat().iter().map(|c| c.code).collect();
+        v.sort();
+        v
+    };
+    for sample in [&a, &b] {
+        for p in 0..N_PILES {
+            assert_eq!(sample.pile(p).len(), lo.pile(p).len());
+            for (c, d) in sample.pile(p).iter().zip(lo.pile(p)) {
+                assert_eq!(c.face_up(), d.face_up());
+                if c.face_up() {
+                    assert_eq!(c, d);
+                }
+            }
+        }
+        assert_eq!(codes(sample), codes(&lo));
+    }
+
+    // after a recycle the stock has all been seen
+    assert_eq!(rules.hidden_places(&lo, 1).len(), 21);
+    let c = rules.sample_hidden(&lo, 1, &mut rng);
+    assert_eq!(c.pile(STOCK), lo.pile(STOCK));
+}
+
+#[test]
+fn test_estimate() {
+    // kings buried under their queens, one card in the stock, so
+    // every sample is the same and the odds are certain
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P1hq P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2skSQ P2hkCQ P2ckDQ P1DK P0P0P0").unwrap();
+    let s = Sampling { samples: 3, ..Default::default() };
+    assert_eq!(rules.win_odds(&lo, 0, &s), 1.0);
+    let odds = rules.estimate(&lo, 0, &s);
+    assert!(!odds.is_empty());
+    assert_eq!((odds[0].won, odds[0].p()), (3, 1.0));
+    assert!(odds.iter().all(|o| o.samples() == 3));
+
+    // a deal, with the same odds on any number of threads
+    use crate::deck::*;
+    let lo = rules.deal(&Deck::from_deal(1));
+    let s = Sampling { samples: 4, seed: 9, budget: Budget { nodes: 2000, time: None },
+        threads: 1 };
+    let odds = rules.estimate(&lo, 0, &s);
+    assert!(odds.windows(2).all(|w| w[0].won >= w[1].won));
+    assert!(odds.iter().all(|o| o.samples() == 4));
+    assert_eq!(rules.estimate(&lo, 0, &Sampling { threads: 3, ..s }), odds);
+    let moves:Vec<Move> = odds.iter().map(|o| o.mv).collect();
+    assert_eq!(rules.estimate_moves(&lo, 0, &moves, &s), odds);
+}
+
+// end mod hindsight
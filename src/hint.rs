@@ -0,0 +1,247 @@
+/*----------------------------------------------------------------------
+Hint module
+
+The next move, without giving away the whole game.  The solver is run
+from the position in normal mode with a budget, so it knows only the
+cards the player can see.  If it finds a win, sure whatever the face
+down cards are, the hint is the first move of the winning line.  That
+is once the rest of the game needs no card the player has not seen,
+such as when the tableau is all face up and the stock has been through
+once, see solver.rs.  If not, lost or out of budget, the legal moves
+are ranked by rules of thumb, each with a reason.  Best first:
+    safe        to foundation, see Rules::is_safe
+    reveal      turns up a face down card, most face down under first
+    foundation  any other move to foundation
+    room        empties a column while a king waits for one
+    king        a king to an empty column
+    frees       leaves a card that can go to foundation
+    waste       from the waste to the tableau
+    draw        from the stock
+    build       any other tableau move
+    recycle     the waste back to the stock
+    back        off a foundation
+Moves the solver never tries, such as a whole column to an empty column,
+are left out.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use crate::klondike::*;
+use crate::layout::*;
+use crate::solver::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reason {
+    Wins,
+    Safe,
+    Reveal,
+    Foundation,
+    Room,
+    King,
+    Frees,
+    Waste,
+    Build,
+    Draw,
+    Recycle,
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hint {
+    pub mv     : Move,
+    pub reason : Reason,
+    pub rank   : i32,                   // higher is better
+}
+
+impl Reason {
+pub fn text(&self) -> &'static str {
+    match self {
+        Reason::Wins       => "leads to a win",
+        Reason::Safe       => "the lower cards of both colors are home",
+        Reason::Reveal     => "turns up a face down card",
+        Reason::Foundation => "plays to the foundation",
+        Reason::Room       => "empties a column for a king",
+        Reason::King       => "puts a king in an empty column",
+        Reason::Frees      => "lets a card go to the foundation next",
+        Reason::Waste      => "plays from the waste",
+        Reason::Build      => "builds on the tableau",
+        Reason::Draw       => "turns up cards from the stock",
+        Reason::Recycle    => "starts through the stock again",
+        Reason::Back       => "takes a card back off the foundation",
+    }
+}}
+
+/*----------------------------------------------------------------------
+Rules of thumb
+*/
+
+// a face up king, other than in pile from, not already at the bottom
+// of a column
+fn king_waiting(lo:&Layout, from:usize) -> bool {
+    let waste = lo.pile(WASTE).last().is_some_and(|c| c.is_king());
+    waste || (TABLEAU..N_PILES).filter(|&t| t != from)
+        .any(|t| lo.pile(t).iter().skip(1).any(|c| c.face_up() && c.is_king()))
+}
+
+pub(crate) fn reason(rules:&Rules, lo:&Layout, mv:&Move) -> (Reason, i32) {
+    if mv.is_draw() {
+        return (Reason::Draw, 35);
+    }
+    if mv.is_recycle() {
+        return (Reason::Recycle, 15);
+    }
+    let src = lo.pile(mv.from);
+    let from_tableau = matches!(role(mv.from), Role::Tableau(_));
+    let under = src.len().checked_sub(mv.n + 1).map(|i| src[i]);
+    let reveals = from_tableau && under.is_some_and(|c| !c.face_up());
+    let face_down = src.iter().filter(|c| !c.face_up()).count() as i32;
+    let frees = from_tableau && under.is_some_and(|c| c.face_up()
+        && (FOUNDATION..TABLEAU).any(|f| match lo.pile(f).last() {
+            Some(top) => top.suit() == c.suit() && top.rank_next(c),
+            None => c.is_ace(),
+        }));
+    let to_empty = lo.pile(mv.to).is_empty();
+    match (role(mv.from), role(mv.to)) {
+        (Role::Foundation(_), _) => (Reason::Back, 5),
+        (_, Role::Foundation(_)) if rules.is_safe(lo, mv) => (Reason::Safe, 200),
+        (_, Role::Foundation(_)) if reveals => (Reason::Reveal, 100 + face_down),
+        (_, Role::Foundation(_)) => (Reason::Foundation, 90),
+        _ if reveals => (Reason::Reveal, 100 + face_down),
+        _ if from_tableau && src.len() == mv.n && king_waiting(lo, mv.from) =>
+            (Reason::Room, 80),
+        _ if to_empty => (Reason::King, 75),
+        _ if frees => (Reason::Frees, 70),
+        (Role::Waste, _) => (Reason::Waste, 50),
+        _ => (Reason::Build, 30),
+    }
+}
+
+// every legal move worth trying, best first
+impl Rules {
+pub fn candidates(&self, lo:&Layout, recycles:usize) -> Vec<Hint> {
+    let mut hints:Vec<Hint> = self.legal_moves(lo, recycles).into_iter()
+        .filter(|mv| score(lo, mv).is_some())
+        .map(|mv| {
+            let (reason, rank) = reason(self, lo, &mv);
+            Hint { mv, reason, rank }
+        })
+        .collect();
+    hints.sort_by_key(|h| -h.rank);
+    hints
+}}
+
+/*----------------------------------------------------------------------
+Hint
+*/
+
+// the winning move alone if the solver finds one, else the candidates
+impl Rules {
+pub fn hint(&self, lo:&Layout, recycles:usize, budget:&Budget) -> Vec<Hint> {
+    let sol = self.solve(lo, recycles, Mode::Normal, budget);
+    match sol.outcome {
+        Outcome::Win(moves) if !moves.is_empty() =>
+            vec![Hint { mv: moves[0], reason: Reason::Wins, rank: i32::MAX }],
+        _ => self.candidates(lo, recycles),
+    }
+}}
+
+// english, such as "move Five of Hearts onto Six of Spades: turns up a
+// face down card"
+impl Rules {
+pub fn describe_hint(&self, lo:&Layout, hint:&Hint) -> String {
+    format!("{}: {}", self.describe(lo, &hint.mv), hint.reason.text())
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[test]
+fn test_candidates() {
+    let rules = Rules::default();
+    // stock empty, a king in the waste, too few cards to win
+    let lo = Layout::from_text(
+        "P0 P1HK P1SA P0P0P0 P2d5S2 P1H8 P1S9 P0P0P0P0").unwrap();
+    let hints = rules.candidates(&lo, 0);
+    let reasons:Vec<Reason> = hints.iter().map(|h| h.reason).collect();
+    use Reason::*;
+    // the ace is not offered to another foundation, see Rules::legal_moves
+    assert_eq!(reasons, [Reveal, Room, King, Recycle]);
+    assert_eq!(hints[0].mv, Move { from: TABLEAU, to: FOUNDATION, n: 1 });
+    assert_eq!(hints[1].mv, Move { from: TABLEAU+1, to: TABLEAU+2, n: 1 });
+    assert_eq!(hints[2].mv, Move { from: WASTE, to: TABLEAU+3, n: 1 });
+    assert!(rules.describe_hint(&lo, &hints[0]).ends_with(": turns up a face down card"));
+
+    // no win to find, so the same
+    assert_eq!(rules.hint(&lo, 0, &Budget::default()), hints);
+}
+
+#[test]
+fn test_hint() {
+    // kings buried under their queens, one card in the stock
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P1hq P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2skSQ P2hkCQ P2ckDQ P1DK P0P0P0").unwrap();
+    let sol = rules.solve(&lo, 0, Mode::Thoughtful, &Budget::default());
+    assert!(matches!(sol.outcome, Outcome::Win(_)));
+    // the win needs the face down cards known, which the player cannot
+    // see, so only rules of thumb
+    assert_eq!(rules.hint(&lo, 0, &Budget::default()), rules.candidates(&lo, 0));
+
+    // all cards in sight, the win is sure
+    let lo = Layout::from_text(
+        "P0 P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2SKHQ P2HKCQ P2CKDQ P2DKSQ P0P0P0").unwrap();
+    let sol = rules.solve(&lo, 0, Mode::Normal, &Budget::default());
+    let Outcome::Win(moves) = sol.outcome else { panic!() };
+    let hints = rules.hint(&lo, 0, &Budget::default());
+    assert_eq!(hints.len(), 1);
+    assert_eq!((hints[0].mv, hints[0].reason), (moves[0], Reason::Wins));
+
+    // out of budget, so only rules of thumb
+    let hints = rules.hint(&lo, 0, &Budget { nodes: 1, time: None });
+    assert!(hints.len() > 1);
+    assert!(hints.windows(2).all(|w| w[0].rank >= w[1].rank));
+    assert_eq!(hints, rules.candidates(&lo, 0));
+}
+
+#[test]
+fn test_hint_mid_game() {
+    use crate::deck::Deck;
+    let rules = Rules::default();
+    let budget = Budget::default();
+    // part way through a real deal, every card seen but five face down
+    // in the stock, gone through twice
+    let mut lo = rules.deal(&Deck::from_deal(1));
+    let Outcome::Win(moves) = rules.solve(&lo, 0, Mode::Thoughtful, &budget).outcome
+        else { panic!() };
+    let mut recycles = 0;
+    for mv in &moves[..73] {
+        rules.apply(&mut lo, recycles, mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+    }
+    assert_eq!((lo.pile(STOCK).len(), recycles), (5, 2));
+    assert!(lo.pile(STOCK).iter().all(|c| !c.face_up()));
+    assert!((TABLEAU..N_PILES).all(|t| lo.pile(t).iter().all(|c| c.face_up())));
+    let Outcome::Win(line) = rules.solve(&lo, recycles, Mode::Normal, &budget).outcome
+        else { panic!() };
+    let hints = rules.hint(&lo, recycles, &budget);
+    assert_eq!(hints.len(), 1);
+    assert_eq!((hints[0].mv, hints[0].reason), (line[0], Reason::Wins));
+    assert!(rules.describe_hint(&lo, &hints[0]).ends_with(": leads to a win"));
+}
+
+// end mod hint
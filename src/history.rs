@@ -0,0 +1,818 @@
+/*----------------------------------------------------------------------
+History module
+
+Keep every position visited in a game, for unlimited undo and redo, and
+keep all the paths traveled.
+
+Each position is stored once, packed, and found again through a hash
+table when it is reached by a different path.  The table is keyed by the
+zobrist hash of the layout and recycle count, see layout.rs, and the
+packed layouts are compared, so a hash shared by two positions does no
+harm.  Each position records the parent it was first reached from, and
+the move from there.  Moves out of a position are kept as a list of
+branches, in the order first played.  Redo follows the branch last
+taken, or any branch chosen.
+
+The path is the list of positions from the start of the game to the
+current one, the way they were actually reached.  Undo steps back along
+the path, not to the first parent, so after a transposition it goes
+back the way the player came.
+
+The count of stock recycles is part of a position, because with limited
+passes it changes which moves are legal.
+
+Marks are a stack of saved positions.  The player can go back to any
+of them at any time, see "Marks" below.
+
+When the rules have auto_move on, safe moves to the foundations are
+played after each move of the player, see "Auto move" below.  Each is
+recorded as a move of its own, so undo takes them back one at a time.
+
+Each branch also records which cards the move put in a different pile
+or a different place in a pile.  This answers the question "where was
+I before this card last moved", see "Card provenance" below.
+
+There is nothing here about display.  The command line, text user
+interface and solver can all share this.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use crate::card::*;
+use crate::klondike::*;
+use crate::layout::*;
+use crate::misc::*;
+
+// index of a position in the history
+pub type PosId = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Branch {
+    pub mv    : Move,
+    pub to    : PosId,
+    pub moved : CardSet,        // cards that changed place
+}
+
+/*----------------------------------------------------------------------
+Set of cards, one bit each, ignoring face up.
+Bit number is 13*suit + rank.
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CardSet {
+    pub bits: u64,
+}
+
+fn card_bit(card:&Card) -> u64 {
+    let n_ranks = Card::info().n_ranks as u64;
+    1 << (card.suit() as u64 * n_ranks + card.rank() as u64)
+}
+
+impl CardSet {
+pub fn contains(&self, card:&Card) -> bool {
+    0 != (self.bits & card_bit(card))
+}}
+
+impl CardSet {
+pub fn insert(&mut self, card:&Card) {
+    self.bits |= card_bit(card);
+}}
+
+impl CardSet {
+pub fn len(&self) -> usize {
+    self.bits.count_ones() as usize
+}}
+
+impl CardSet {
+pub fn is_empty(&self) -> bool {
+    0 == self.bits
+}}
+
+// cards whose pile or place in pile differs between two layouts
+pub fn moved_cards(before:&Layout, after:&Layout) -> CardSet {
+    let n_cards = Card::info().n_cards;
+    let places = |lo:&Layout| {
+        let mut at:Vec<Option<(usize, usize)>> = vec![None; n_cards];
+        for (ipile, pile) in lo.piles().iter().enumerate() {
+            for (i, card) in pile.iter().enumerate() {
+                at[card_bit(card).trailing_zeros() as usize] = Some((ipile, i));
+            }
+        }
+        at
+    };
+    let (a, b) = (places(before), places(after));
+    let mut set = CardSet::default();
+    for i in 0..n_cards {
+        if a[i] != b[i] {
+            set.bits |= 1 << i;
+        }
+    }
+    set
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub packed   : LayoutPacked,
+    pub recycles : usize,
+    pub parent   : Option<PosId>,       // first reached from
+    pub mv       : Option<Move>,        // move from parent
+    pub branches : Vec<Branch>,         // moves played from here
+    pub redo     : Option<usize>,       // branch redo will follow
+}
+
+// a saved position, and the path that reached it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mark {
+    pub name : String,
+    pub path : Vec<PosId>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    nodes : Vec<Node>,
+    index : PassThroughMap<u64, Vec<PosId>>,   // by key(), see below
+    path  : Vec<PosId>,
+    marks : Vec<Mark>,
+}
+
+/*----------------------------------------------------------------------
+Construct and inspect
+
+Layouts given to the history must pack, which any klondike layout does.
+*/
+
+fn pack(lo:&Layout) -> LayoutPacked {
+    lo.pack().expect("layout in history must pack")
+}
+
+fn key(lo:&Layout, recycles:usize) -> u64 {
+    lo.zobrist() ^ zobrist_count(recycles)
+}
+
+impl History {
+pub fn new(lo:&Layout) -> History {
+    let mut h = History::default();
+    let id = h.insert(key(lo, 0), pack(lo), 0, None, None);
+    h.path.push(id);
+    h
+}}
+
+impl History {
+pub fn current(&self) -> PosId {
+    *self.path.last().unwrap()
+}}
+
+impl History {
+pub fn node(&self, id:PosId) -> &Node {
+    &self.nodes[id]
+}}
+
+// number of distinct positions
+impl History {
+pub fn len(&self) -> usize {
+    self.nodes.len()
+}}
+
+impl History {
+pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+}}
+
+impl History {
+pub fn layout(&self) -> Layout {
+    self.layout_at(self.current())
+}}
+
+impl History {
+pub fn layout_at(&self, id:PosId) -> Layout {
+    Layout::unpack(self.nodes[id].packed).unwrap()
+}}
+
+impl History {
+pub fn recycles(&self) -> usize {
+    self.nodes[self.current()].recycles
+}}
+
+// positions from the start of the game to the current one
+impl History {
+pub fn path(&self) -> &[PosId] {
+    &self.path
+}}
+
+// layouts along the path, for kpat export
+impl History {
+pub fn line(&self) -> Vec<Layout> {
+    self.path.iter().map(|id| self.layout_at(*id)).collect()
+}}
+
+// moves along the path
+impl History {
+pub fn moves(&self) -> Vec<Move> {
+    self.path.windows(2)
+        .map(|w| self.branch_to(w[0], w[1]).unwrap().mv)
+        .collect()
+}}
+
+// number of moves made to reach the current position
+impl History {
+pub fn move_number(&self) -> usize {
+    self.path.len() - 1
+}}
+
+impl History {
+pub fn find(&self, lo:&Layout, recycles:usize) -> Option<PosId> {
+    self.lookup(key(lo, recycles), &pack(lo), recycles)
+}}
+
+impl History {
+fn lookup(&self, key:u64, packed:&LayoutPacked, recycles:usize) -> Option<PosId> {
+    self.index.get(&key)?.iter().copied().find(|id| {
+        let node = &self.nodes[*id];
+        node.packed == *packed && node.recycles == recycles
+    })
+}}
+
+impl History {
+fn branch_to(&self, from:PosId, to:PosId) -> Option<Branch> {
+    self.nodes[from].branches.iter().find(|b| b.to == to).copied()
+}}
+
+impl History {
+fn insert(&mut self, key:u64, packed:LayoutPacked, recycles:usize,
+          parent:Option<PosId>, mv:Option<Move>) -> PosId
+{
+    let id = self.nodes.len();
+    self.nodes.push(Node {
+        packed, recycles, parent, mv,
+        branches : vec![],
+        redo     : None,
+    });
+    self.index.entry(key).or_default().push(id);
+    id
+}}
+
+/*----------------------------------------------------------------------
+Record a move
+
+push records a move that the caller has already applied, giving the
+layout after it.  play applies the move with the rules and records it.
+Either way the current position becomes the one after the move, and
+redo from the position before will follow this move.
+*/
+
+impl History {
+pub fn push(&mut self, mv:Move, lo:&Layout, recycles:usize) -> PosId {
+    let from = self.current();
+    let moved = moved_cards(&self.layout(), lo);
+    let packed = pack(lo);
+    let key = key(lo, recycles);
+    let to = match self.lookup(key, &packed, recycles) {
+        Some(id) => id,
+        None => self.insert(key, packed, recycles, Some(from), Some(mv)),
+    };
+    let node = &mut self.nodes[from];
+    let ib = match node.branches.iter().position(|b| b.to == to && b.mv == mv) {
+        Some(ib) => ib,
+        None => {
+            node.branches.push(Branch { mv, to, moved });
+            node.branches.len() - 1
+        },
+    };
+    node.redo = Some(ib);
+    self.path.push(to);
+    to
+}}
+
+impl History {
+pub fn play(&mut self, rules:&Rules, mv:&Move) -> Result<PosId, MoveError> {
+    let mut lo = self.layout();
+    let recycles = self.recycles();
+    rules.apply(&mut lo, recycles, mv)?;
+    let recycles = recycles + mv.is_recycle() as usize;
+    Ok(self.push(*mv, &lo, recycles))
+}}
+
+/*----------------------------------------------------------------------
+Auto move
+
+auto_move plays safe moves to the foundations, see Rules::is_safe, one
+after another until there are none, and gives back the moves played.
+A card that may still be wanted on the tableau stays for the player to
+move, so auto_move never loses a game that could be won.
+Nothing is played when the rules have auto_move off.  play_auto plays a
+move of the player, then auto_move.  A game can call auto_move after
+the deal too, since an ace may be showing.
+*/
+
+impl History {
+pub fn auto_move(&mut self, rules:&Rules) -> Vec<Move> {
+    let mut moves = vec![];
+    if !rules.auto_move {
+        return moves;
+    }
+    while let Some(mv) = rules.safe_move(&self.layout()) {
+        self.play(rules, &mv).expect("safe move is legal");
+        moves.push(mv);
+    }
+    moves
+}}
+
+impl History {
+pub fn play_auto(&mut self, rules:&Rules, mv:&Move) -> Result<Vec<Move>, MoveError> {
+    self.play(rules, mv)?;
+    Ok(self.auto_move(rules))
+}}
+
+/*----------------------------------------------------------------------
+Undo and redo
+*/
+
+impl History {
+pub fn can_undo(&self) -> bool {
+    self.path.len() > 1
+}}
+
+impl History {
+pub fn undo(&mut self) -> Option<PosId> {
+    if !self.can_undo() {
+        return None;
+    }
+    let to = self.path.pop().unwrap();
+    let from = self.current();
+    // redo goes back where we just were
+    let node = &mut self.nodes[from];
+    node.redo = node.branches.iter().position(|b| b.to == to);
+    Some(from)
+}}
+
+// moves that redo could follow from the current position
+impl History {
+pub fn redo_branches(&self) -> &[Branch] {
+    &self.nodes[self.current()].branches
+}}
+
+impl History {
+pub fn can_redo(&self) -> bool {
+    self.nodes[self.current()].redo.is_some()
+}}
+
+impl History {
+pub fn redo(&mut self) -> Option<PosId> {
+    let ib = self.nodes[self.current()].redo?;
+    self.redo_branch(ib)
+}}
+
+// redo along branch ib of redo_branches
+impl History {
+pub fn redo_branch(&mut self, ib:usize) -> Option<PosId> {
+    let from = self.current();
+    let to = self.nodes[from].branches.get(ib)?.to;
+    self.nodes[from].redo = Some(ib);
+    self.path.push(to);
+    Some(to)
+}}
+
+/*----------------------------------------------------------------------
+Marks
+
+push_mark saves the current position on top of the stack of marks.  It
+is named by the caller, or else numbered one more than the highest
+number among the marks on the stack, so a number freed by popping or
+deleting may be used again.  goto_mark returns to a mark along the
+path that first reached it.  Nothing is lost.  Positions played after
+the mark stay in the history, and redo from the mark follows them.
+Marks stay on the stack until popped or deleted.
+
+Marks are listed bottom of stack first, with the move number and the
+text notation of the position.
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkSummary {
+    pub name        : String,
+    pub id          : PosId,
+    pub move_number : usize,
+    pub text        : String,
+}
+
+impl Mark {
+pub fn id(&self) -> PosId {
+    *self.path.last().unwrap()
+}}
+
+impl History {
+pub fn push_mark(&mut self, name:Option<&str>) -> usize {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            // one more than the highest number on the stack
+            let n = self.marks.iter()
+                .filter_map(|m| m.name.parse::<usize>().ok())
+                .max()
+                .unwrap_or(0);
+            format!("{}", n + 1)
+        },
+    };
+    self.marks.push(Mark { name, path: self.path.clone() });
+    self.marks.len() - 1
+}}
+
+impl History {
+pub fn marks(&self) -> &[Mark] {
+    &self.marks
+}}
+
+impl History {
+pub fn find_mark(&self, name:&str) -> Option<usize> {
+    self.marks.iter().position(|m| m.name == name)
+}}
+
+impl History {
+pub fn list_marks(&self) -> Vec<MarkSummary> {
+    self.marks.iter().map(|m| MarkSummary {
+        name        : m.name.clone(),
+        id          : m.id(),
+        move_number : m.path.len() - 1,
+        text        : self.layout_at(m.id()).to_text(),
+    }).collect()
+}}
+
+impl History {
+pub fn goto_mark(&mut self, imark:usize) -> Option<PosId> {
+    let path = self.marks.get(imark)?.path.clone();
+    // redo from each position along the way follows the path
+    for w in path.windows(2) {
+        let node = &mut self.nodes[w[0]];
+        node.redo = node.branches.iter().position(|b| b.to == w[1]);
+    }
+    // and from the mark, toward where we were, if that is beyond it
+    let id = *path.last().unwrap();
+    if let Some(k) = self.path.iter().position(|p| *p == id) {
+        if let Some(next) = self.path.get(k + 1) {
+            let node = &mut self.nodes[id];
+            node.redo = node.branches.iter().position(|b| b.to == *next);
+        }
+    }
+    self.path = path;
+    Some(id)
+}}
+
+impl History {
+pub fn pop_mark(&mut self) -> Option<Mark> {
+    self.marks.pop()
+}}
+
+impl History {
+pub fn delete_mark(&mut self, imark:usize) -> Option<Mark> {
+    if imark < self.marks.len() {
+        Some(self.marks.remove(imark))
+    } else {
+        None
+    }
+}}
+
+/*----------------------------------------------------------------------
+Card provenance
+
+Given a card, walk back along the path to the most recent move that
+changed the place of that card.  The answer is the position just before
+that move.  Turning a card over where it lies does not count.
+
+goto_before_card_moved goes there as if by undo, so redo can replay the
+moves since.
+*/
+
+impl History {
+pub fn before_card_moved(&self, card:&Card) -> Option<PosId> {
+    self.before_card_moved_index(card).map(|k| self.path[k])
+}}
+
+// index in path of the position before the move
+impl History {
+fn before_card_moved_index(&self, card:&Card) -> Option<usize> {
+    (1..self.path.len()).rev().find(|k| {
+        let b = self.branch_to(self.path[k-1], self.path[*k]).unwrap();
+        b.moved.contains(card)
+    }).map(|k| k - 1)
+}}
+
+impl History {
+pub fn goto_before_card_moved(&mut self, card:&Card) -> Option<PosId> {
+    let k = self.before_card_moved_index(card)?;
+    while self.path.len() > k + 1 {
+        self.undo();
+    }
+    Some(self.current())
+}}
+
+/*----------------------------------------------------------------------
+Take apart and put back together, for save files
+
+from_parts trusts the parts to fit together, save.rs checks them
+first.  The hash index, and the cards moved on each branch, are worked
+out again from the layouts.
+*/
+
+impl History {
+pub fn nodes(&self) -> &[Node] {
+    &self.nodes
+}}
+
+impl History {
+pub fn from_parts(nodes:Vec<Node>, path:Vec<PosId>, marks:Vec<Mark>) -> History {
+    let mut h = History { nodes, path, marks, ..Default::default() };
+    for id in 0..h.nodes.len() {
+        let before = h.layout_at(id);
+        let key = key(&before, h.nodes[id].recycles);
+        h.index.entry(key).or_default().push(id);
+        for ib in 0..h.nodes[id].branches.len() {
+            let to = h.nodes[id].branches[ib].to;
+            let moved = moved_cards(&before, &h.layout_at(to));
+            h.nodes[id].branches[ib].moved = moved;
+        }
+    }
+    h
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+#[cfg(test)]
+fn test_game() -> (Rules, History) {
+    use crate::deck::Deck;
+    let rules = Rules::default();
+    let lo = rules.deal(&Deck::from_deal(3));
+    (rules, History::new(&lo))
+}
+
+#[test]
+fn test_undo_redo() {
+    let (rules, mut h) = test_game();
+    let start = h.layout();
+    assert!(!h.can_undo());
+    assert!(!h.can_redo());
+    assert_eq!(h.undo(), None);
+    assert_eq!(h.redo(), None);
+
+    // play some moves, keeping layouts along the way
+    let mut layouts = vec![start.clone()];
+    for _ in 0..10 {
+        let mv = rules.legal_moves(&h.layout(), h.recycles())[0];
+        h.play(&rules, &mv).unwrap();
+        layouts.push(h.layout());
+    }
+    assert_eq!(h.move_number(), 10);
+    assert_eq!(h.line(), layouts);
+    assert_eq!(h.moves().len(), 10);
+
+    // undo all the way, then redo all the way
+    for i in (0..10).rev() {
+        h.undo().unwrap();
+        assert_eq!(h.layout(), layouts[i]);
+    }
+    assert!(!h.can_undo());
+    for lo in &layouts[1..] {
+        h.redo().unwrap();
+        assert_eq!(&h.layout(), lo);
+    }
+    assert!(!h.can_redo());
+
+    // illegal move is refused and changes nothing
+    let n = h.len();
+    let bad = Move { from:TABLEAU, to:TABLEAU, n:1 };
+    assert!(h.play(&rules, &bad).is_err());
+    assert_eq!(h.len(), n);
+    assert_eq!(h.move_number(), 10);
+}
+
+#[test]
+fn test_branches() {
+    let (rules, mut h) = test_game();
+    let moves = rules.legal_moves(&h.layout(), 0);
+    assert!(moves.len() >= 2);
+
+    // two different moves from the start
+    let a = h.play(&rules, &moves[0]).unwrap();
+    h.undo();
+    let b = h.play(&rules, &moves[1]).unwrap();
+    h.undo();
+    assert_ne!(a, b);
+    assert_eq!(h.redo_branches().len(), 2);
+
+    // redo follows the last one taken, or any one chosen
+    assert_eq!(h.redo(), Some(b));
+    h.undo();
+    assert_eq!(h.redo_branch(0), Some(a));
+    h.undo();
+    assert_eq!(h.redo(), Some(a));
+    h.undo();
+    assert_eq!(h.redo_branch(2), None);
+    assert_eq!(h.node(a).parent, Some(0));
+    assert_eq!(h.node(a).mv, Some(moves[0]));
+}
+
+#[test]
+fn test_transposition() {
+    let (rules, mut h) = test_game();
+    // draw through the whole stock and recycle, back to the start layout
+    // but with one recycle done, so a different position
+    let start = h.layout();
+    while !h.layout().pile(STOCK).is_empty() {
+        let lo = h.layout();
+        let draw = Move { from:STOCK, to:WASTE, n:3.min(lo.pile(STOCK).len()) };
+        h.play(&rules, &draw).unwrap();
+    }
+    let n = h.layout().pile(WASTE).len();
+    h.play(&rules, &Move { from:WASTE, to:STOCK, n }).unwrap();
+    assert_eq!(h.layout(), start);
+    assert_eq!(h.recycles(), 1);
+    assert_ne!(h.current(), 0);
+
+    // reach the same position two ways, it is stored once
+    let lo = Layout::from_text("P0 P1DA P0P0P0P0 P1SA P0P0P0P0P0P0").unwrap();
+    let mut h = History::new(&lo);
+    let m1 = Move::from_text("W>F1").unwrap();
+    let m2 = Move::from_text("T1>F2").unwrap();
+    h.play(&rules, &m1).unwrap();
+    let end1 = h.play(&rules, &m2).unwrap();
+    h.undo();
+    h.undo();
+    h.play(&rules, &m2).unwrap();
+    let end2 = h.play(&rules, &m1).unwrap();
+    assert_eq!(end1, end2);
+    assert_eq!(h.len(), 4);
+    assert_eq!(h.node(end1).parent, Some(1));
+    // undo goes back the way we came, through m2
+    h.undo();
+    assert_eq!(h.moves(), vec![m2]);
+    assert_eq!(h.redo(), Some(end1));
+}
+
+#[test]
+fn test_marks() {
+    let (rules, mut h) = test_game();
+    let play = |h:&mut History, n:usize| {
+        for _ in 0..n {
+            let mv = rules.legal_moves(&h.layout(), h.recycles())[0];
+            h.play(&rules, &mv).unwrap();
+        }
+    };
+    assert_eq!(h.push_mark(None), 0);
+    play(&mut h, 3);
+    assert_eq!(h.push_mark(Some("before king")), 1);
+    let at_king = h.layout();
+    play(&mut h, 4);
+    assert_eq!(h.push_mark(None), 2);
+    let end = h.current();
+
+    let list = h.list_marks();
+    let names:Vec<&str> = list.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["1", "before king", "2"]);
+    let numbers:Vec<usize> = list.iter().map(|m| m.move_number).collect();
+    assert_eq!(numbers, vec![0, 3, 7]);
+    assert_eq!(list[1].text, at_king.to_text());
+
+    // go back to a mark, the moves after it are kept for redo
+    let i = h.find_mark("before king").unwrap();
+    h.goto_mark(i).unwrap();
+    assert_eq!(h.layout(), at_king);
+    assert_eq!(h.move_number(), 3);
+    for _ in 0..4 {
+        h.redo().unwrap();
+    }
+    assert_eq!(h.current(), end);
+
+    // a new line from the mark, then back to the mark at its end
+    h.goto_mark(0).unwrap();
+    assert_eq!(h.move_number(), 0);
+    let moves = rules.legal_moves(&h.layout(), 0);
+    h.play(&rules, moves.last().unwrap()).unwrap();
+    h.goto_mark(2).unwrap();
+    assert_eq!(h.current(), end);
+    assert_eq!(h.move_number(), 7);
+
+    // pop and delete
+    assert_eq!(h.pop_mark().unwrap().name, "2");
+    assert_eq!(h.delete_mark(0).unwrap().name, "1");
+    assert_eq!(h.delete_mark(5), None);
+    assert_eq!(h.marks().len(), 1);
+    assert_eq!(h.goto_mark(1), None);
+    // numbering carries on from the highest left
+    h.push_mark(None);
+    assert_eq!(h.marks().last().unwrap().name, "1");
+}
+
+#[test]
+fn test_auto_move() {
+    // draw one, aces up, red twos showing, nothing in the stock
+    let rules = Rules::draw1();
+    let lo = Layout::from_text(
+        "P0 P1H2 P2SAS2 P1DA P1CA P1HA P2s9D2 P1S3 P0P0P0P0P0").unwrap();
+    let mut h = History::new(&lo);
+    let s3 = Move { from: TABLEAU+1, to: FOUNDATION, n: 1 };
+    let h2 = Move { from: WASTE, to: FOUNDATION+3, n: 1 };
+    let d2 = Move { from: TABLEAU, to: FOUNDATION+1, n: 1 };
+    // 3S was not safe, but the red twos are, and 9S turned up is not
+    assert_eq!(h.play_auto(&rules, &s3), Ok(vec![h2, d2]));
+    assert_eq!(h.moves(), [s3, h2, d2]);
+    assert!(h.layout().pile(TABLEAU)[0].face_up());
+
+    // undo takes back one move at a time
+    h.undo();
+    assert_eq!(h.moves(), [s3, h2]);
+    assert!(!h.layout().pile(TABLEAU)[0].face_up());
+    h.undo();
+    h.undo();
+    assert_eq!(h.layout(), lo);
+
+    // nothing for purists, and nothing played when the move is not legal
+    let manual = Rules { auto_move: false, ..rules };
+    assert_eq!(h.play_auto(&manual, &s3), Ok(vec![]));
+    assert_eq!(h.move_number(), 1);
+    assert_eq!(h.play_auto(&rules, &s3), Err(MoveError::NotEnoughCards));
+    assert_eq!(h.auto_move(&rules), [h2, d2]);
+    assert_eq!(h.move_number(), 3);
+
+    // the five of hearts could go up, but the four of spades may have to
+    // come down onto it, see test_solve_not_safe in solver.rs
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P0 P0 P4SAS2S3S4 P4CAC2C3C4 P4HAH2H3H4 P1DA P1H5 P2d2D3 \
+         P36ckskhkdkcqsqhqdqcjsjhjdjctsthtdtc9s9h9d9c8s8h8d8c7s7h7d7c6s6h6d6c5s5d5D4 \
+         P0P0P0P0").unwrap();
+    let h5 = Move { from: TABLEAU, to: FOUNDATION+2, n: 1 };
+    assert!(rules.check(&lo, 0, &h5).is_ok());
+    let mut h = History::new(&lo);
+    assert_eq!(h.auto_move(&rules), []);
+    // once it holds the three of diamonds, the two and three go up
+    let s4 = Move { from: FOUNDATION, to: TABLEAU, n: 1 };
+    let d3 = Move { from: TABLEAU+1, to: TABLEAU, n: 1 };
+    assert_eq!(h.play_auto(&rules, &s4), Ok(vec![]));
+    let up = h.play_auto(&rules, &d3).unwrap();
+    assert_eq!(up[..2], [Move { from: TABLEAU+1, to: FOUNDATION+3, n: 1 },
+                         Move { from: TABLEAU, to: FOUNDATION+3, n: 1 }]);
+}
+
+#[test]
+fn test_moved_cards() {
+    let rules = Rules::default();
+    let before = Layout::from_text(
+        "P2d9dt P1DA P0P0P0P0 P3c5H6S5 P1C7 P0P0P0P0P0").unwrap();
+    let t = |text:&str, want:&[&str]| {
+        let mut lo = before.clone();
+        rules.apply(&mut lo, 0, &Move::from_text(text).unwrap()).unwrap();
+        let set = moved_cards(&before, &lo);
+        assert_eq!(set.len(), want.len(), "{}", text);
+        for w in want {
+            let c = Layout::from_text(&format!("P1{}", w)).unwrap().pile(0)[0];
+            assert!(set.contains(&c), "{} {}", text, w);
+        }
+    };
+    // the five of clubs turned over in place does not count
+    t("T1-2>T2", &["H6", "S5"]);
+    t("W>F1",    &["DA"]);
+    t("D2",      &["D9", "DT"]);
+}
+
+#[test]
+fn test_before_card_moved() {
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P3s2s3s4 P1DA P0P0P0P0 P3c5H6S5 P1C7 P1D8 P0P0P0P0").unwrap();
+    let mut h = History::new(&lo);
+    let card = |t:&str| Layout::from_text(&format!("P1{}", t)).unwrap().pile(0)[0];
+    let play = |h:&mut History, t:&str| {
+        h.play(&rules, &Move::from_text(t).unwrap()).unwrap()
+    };
+    play(&mut h, "T1-2>T2");            // 6H 5S onto 7C
+    play(&mut h, "W>F1");               // AD up
+    play(&mut h, "D3");
+    play(&mut h, "T2-3>T3");            // 7C 6H 5S onto 8D
+    let last = play(&mut h, "R3");
+    let at = h.path().to_vec();
+
+    assert_eq!(h.before_card_moved(&card("S5")), Some(at[3]));
+    assert_eq!(h.before_card_moved(&card("C7")), Some(at[3]));
+    assert_eq!(h.before_card_moved(&card("DA")), Some(at[1]));
+    assert_eq!(h.before_card_moved(&card("S4")), Some(at[4]));
+    assert_eq!(h.before_card_moved(&card("D8")), None);
+    // turned over, never moved
+    assert_eq!(h.before_card_moved(&card("c5")), None);
+
+    // jump there, then redo back
+    assert_eq!(h.goto_before_card_moved(&card("C7")), Some(at[3]));
+    assert_eq!(h.move_number(), 3);
+    h.redo();
+    h.redo();
+    assert_eq!(h.current(), last);
+    assert_eq!(h.goto_before_card_moved(&card("DA")), Some(at[1]));
+    assert_eq!(h.goto_before_card_moved(&card("D8")), None);
+    assert_eq!(h.move_number(), 1);
+}
+
+// end mod history -----------------------------------------------------
@@ -0,0 +1,1128 @@
+/*----------------------------------------------------------------------
+Klondike module
+
+Rules of klondike solitaire on top of a Layout.
+
+The layout knows only about numbered piles.  This module assigns a role
+to each pile index, and knows which moves the rules allow between them.
+
+=====   ===========   ==========
+index   role          kpat name
+=====   ===========   ==========
+  0     stock         talon
+  1     waste         pile
+ 2..5   foundation    target0..3
+ 6..12  tableau       play0..6
+=====   ===========   ==========
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use crate::card::*;
+use crate::deck::*;
+use crate::layout::*;
+
+/*----------------------------------------------------------------------
+Pile roles
+*/
+
+pub const STOCK:usize = 0;
+pub const WASTE:usize = 1;
+pub const FOUNDATION:usize = 2;      // first foundation
+pub const N_FOUNDATIONS:usize = 4;
+pub const TABLEAU:usize = FOUNDATION + N_FOUNDATIONS;    // first tableau
+pub const N_TABLEAUX:usize = 7;
+pub const N_PILES:usize = TABLEAU + N_TABLEAUX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Stock,
+    Waste,
+    Foundation(usize),      // 0..4
+    Tableau(usize),         // 0..7
+}
+
+pub fn role(ipile:usize) -> Role {
+    match ipile {
+        STOCK => Role::Stock,
+        WASTE => Role::Waste,
+        i if i < TABLEAU => Role::Foundation(i - FOUNDATION),
+        i if i < N_PILES => Role::Tableau(i - TABLEAU),
+        _ => panic!("no klondike pile {}", ipile),
+    }
+}
+
+#[test]
+fn test_role() {
+    assert_eq!(role(0), Role::Stock);
+    assert_eq!(role(1), Role::Waste);
+    assert_eq!(role(2), Role::Foundation(0));
+    assert_eq!(role(5), Role::Foundation(3));
+    assert_eq!(role(6), Role::Tableau(0));
+    assert_eq!(role(12), Role::Tableau(6));
+}
+
+/*----------------------------------------------------------------------
+A move of n cards from the tail of one pile to the tail of another.
+
+Two moves involve the stock and are special.  Both turn the moved cards
+over, as a person would, rather than keeping their order.
+
+  draw      stock -> waste, n is the draw count, or fewer if the stock
+            is nearly empty.  The cards are dealt one at a time, so the
+            top of the stock ends up at the bottom of those drawn.
+
+  recycle   waste -> stock, n is the whole waste.  Only allowed when
+            the stock is empty.  The waste is turned over as a stack,
+            so the first card drawn becomes the top of the stock again.
+
+This matches the talon and pile handling of kpat.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from : usize,
+    pub to   : usize,
+    pub n    : usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    NoSuchPile,         // pile index out of range
+    SamePile,           // from and to are the same
+    NotEnoughCards,     // source pile is shorter than n
+    FaceDown,           // would move a face down card
+    BadSequence,        // moved cards are not a built sequence
+    BadSource,          // cannot take cards from this pile
+    BadTarget,          // cannot put cards on this pile
+    NoBuild,            // cards do not build on target
+    BadDraw,            // wrong number of cards drawn from stock
+    StockNotEmpty,      // recycle while cards remain in stock
+    NoPassesLeft,       // recycle would exceed pass limit
+}
+
+impl std::fmt::Display for MoveError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use MoveError::*;
+    let s = match self {
+        NoSuchPile      => "no such pile",
+        SamePile        => "source and target are the same pile",
+        NotEnoughCards  => "not enough cards in source pile",
+        FaceDown        => "cannot move a face down card",
+        BadSequence     => "cards are not in sequence",
+        BadSource       => "cannot move cards from that pile",
+        BadTarget       => "cannot move cards to that pile",
+        NoBuild         => "cards do not build on target pile",
+        BadDraw         => "wrong number of cards drawn from stock",
+        StockNotEmpty   => "stock must be empty to recycle waste",
+        NoPassesLeft    => "no passes through the stock left",
+    };
+    write!(f, "{}", s)
+}}
+
+impl std::error::Error for MoveError {}
+
+/*----------------------------------------------------------------------
+Rules
+
+draw       number of cards dealt from stock to waste at a time, 1 or 3
+passes     how many times the player may go through the stock
+auto_move  safe cards go to the foundations by themselves, see
+           "Safe moves" below, on unless turned off
+
+The number of passes used so far is not part of the layout.  Callers
+keep count of recycle moves and pass it in where it matters.  A game
+begins on its first pass with zero recycles.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Passes {
+    Unlimited,
+    Limited(usize),     // total passes, including the first
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub draw      : usize,
+    pub passes    : Passes,
+    pub auto_move : bool,
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules { draw: 3, passes: Passes::Unlimited, auto_move: true }
+    }
+}
+
+impl Rules {
+pub fn new(draw:usize, passes:Passes) -> Rules {
+    assert!(draw > 0);
+    Rules { draw, passes, auto_move: true }
+}}
+
+// draw one, three passes
+impl Rules {
+pub fn draw1() -> Rules {
+    Rules::new(1, Passes::Limited(3))
+}}
+
+// draw three, one pass only
+impl Rules {
+pub fn vegas() -> Rules {
+    Rules::new(3, Passes::Limited(1))
+}}
+
+// recycle is allowed after this many recycles already done
+impl Rules {
+pub fn can_recycle(&self, recycles:usize) -> bool {
+    match self.passes {
+        Passes::Unlimited => true,
+        Passes::Limited(n) => recycles + 1 < n,
+    }
+}}
+
+// draw count, then passes or "unlimited", as in save and deal files.
+// Auto move is not part of it, and is on when read back.  Save files
+// keep it on a line of its own, see save.rs.
+impl Rules {
+pub fn to_text(&self) -> String {
+    match self.passes {
+        Passes::Unlimited => format!("{} unlimited", self.draw),
+        Passes::Limited(n) => format!("{} {}", self.draw, n),
+    }
+}}
+
+impl Rules {
+pub fn from_text(text:&str) -> Option<Rules> {
+    let words:Vec<&str> = text.split_whitespace().collect();
+    let [draw, passes] = words[..] else { return None };
+    let draw:usize = draw.parse().ok().filter(|d| *d > 0)?;
+    let passes = match passes {
+        "unlimited" => Passes::Unlimited,
+        n => Passes::Limited(n.parse().ok()?),
+    };
+    Some(Rules::new(draw, passes))
+}}
+
+#[test]
+fn test_rules_text() {
+    for rules in [Rules::default(), Rules::draw1(), Rules::vegas()] {
+        assert_eq!(Rules::from_text(&rules.to_text()), Some(rules));
+    }
+    assert_eq!(Rules::vegas().to_text(), "3 1");
+    assert_eq!(Rules::from_text(" 1  unlimited "),
+        Some(Rules::new(1, Passes::Unlimited)));
+    let manual = Rules { auto_move: false, ..Rules::draw1() };
+    assert_eq!(manual.to_text(), "1 3");
+    for bad in ["", "3", "0 unlimited", "3 lots", "3 1 1", "3 1 manual"] {
+        assert_eq!(Rules::from_text(bad), None, "{}", bad);
+    }
+}
+
+/*----------------------------------------------------------------------
+Deal a deck into the starting layout
+
+Cards are taken from the front of the deck and dealt across the tableau
+one row at a time, as a person would deal.  Tableau pile k gets k+1
+cards, and the last card on each is turned face up.  What is left goes
+to the stock, face down, with the last card of the deck on top.
+*/
+
+impl Rules {
+pub fn deal(&self, deck:&Deck) -> Layout {
+    let cards = vec_card_from_vec_u8(deck.cards.clone());
+    let mut piles:Vec<Vec<Card>> = vec![vec![]; N_PILES];
+    let mut i = 0;
+    for row in 0..N_TABLEAUX {
+        for col in row..N_TABLEAUX {
+            let mut c = cards[i];
+            c.set_face_up(row == col);
+            piles[TABLEAU + col].push(c);
+            i += 1;
+        }
+    }
+    for c in &cards[i..] {
+        let mut c = *c;
+        c.set_face_up(false);
+        piles[STOCK].push(c);
+    }
+    Layout::new(piles)
+}}
+
+/*----------------------------------------------------------------------
+Building rules for single cards
+*/
+
+// card can go onto foundation whose top card is top
+fn builds_on_foundation(top:Option<&Card>, card:&Card) -> bool {
+    match top {
+        None => card.is_ace(),
+        Some(t) => t.suit() == card.suit() && t.rank_next(*card),
+    }
+}
+
+// card can go onto tableau whose top card is top
+fn builds_on_tableau(top:Option<&Card>, card:&Card) -> bool {
+    match top {
+        None => card.is_king(),
+        Some(t) => t.face_up() && !t.same_color(card) && card.rank_next(*t),
+    }
+}
+
+// cards are face up, descending and alternating in color
+fn is_built_sequence(cards:&[Card]) -> bool {
+    cards.iter().all(|c| c.face_up())
+    && cards.windows(2).all(|w|
+        !w[0].same_color(&w[1]) && w[1].rank_next(w[0])
+    )
+}
+
+/*----------------------------------------------------------------------
+Stock moves
+*/
+
+impl Move {
+pub fn is_draw(&self) -> bool {
+    self.from == STOCK && self.to == WASTE
+}}
+
+impl Move {
+pub fn is_recycle(&self) -> bool {
+    self.from == WASTE && self.to == STOCK
+}}
+
+impl Rules {
+fn draw_move(&self, lo:&Layout) -> Move {
+    let n = self.draw.min(lo.pile(STOCK).len());
+    Move { from:STOCK, to:WASTE, n }
+}}
+
+impl Rules {
+fn recycle_move(&self, lo:&Layout) -> Move {
+    Move { from:WASTE, to:STOCK, n:lo.pile(WASTE).len() }
+}}
+
+impl Rules {
+fn check_stock(&self, lo:&Layout, recycles:usize, mv:&Move)
+    -> Result<(), MoveError>
+{
+    use MoveError::*;
+    if mv.is_draw() {
+        if lo.pile(STOCK).is_empty() {
+            return Err(NotEnoughCards);
+        }
+        if *mv != self.draw_move(lo) {
+            return Err(BadDraw);
+        }
+    } else {
+        if !lo.pile(STOCK).is_empty() {
+            return Err(StockNotEmpty);
+        }
+        if lo.pile(WASTE).is_empty() || *mv != self.recycle_move(lo) {
+            return Err(NotEnoughCards);
+        }
+        if !self.can_recycle(recycles) {
+            return Err(NoPassesLeft);
+        }
+    }
+    Ok(())
+}}
+
+/*----------------------------------------------------------------------
+Validate a move
+*/
+
+impl Rules {
+pub fn check(&self, lo:&Layout, recycles:usize, mv:&Move)
+    -> Result<(), MoveError>
+{
+    use MoveError::*;
+    if mv.from >= N_PILES || mv.to >= N_PILES || lo.piles().len() != N_PILES {
+        return Err(NoSuchPile);
+    }
+    if mv.from == mv.to {
+        return Err(SamePile);
+    }
+    if mv.is_draw() || mv.is_recycle() {
+        return self.check_stock(lo, recycles, mv);
+    }
+    let src = lo.pile(mv.from);
+    if 0 == mv.n || mv.n > src.len() {
+        return Err(NotEnoughCards);
+    }
+    let cards = &src[src.len()-mv.n..];
+    if !cards[0].face_up() {
+        return Err(FaceDown);
+    }
+    // only tableau piles can give up more than one card
+    match role(mv.from) {
+        Role::Stock => return Err(BadSource),
+        Role::Waste | Role::Foundation(_) if mv.n > 1 => return Err(BadSequence),
+        _ => {},
+    }
+    if !is_built_sequence(cards) {
+        return Err(BadSequence);
+    }
+    let top = lo.pile(mv.to).last();
+    let ok = match role(mv.to) {
+        Role::Stock | Role::Waste => return Err(BadTarget),
+        Role::Foundation(_) => {
+            if mv.n > 1 {
+                return Err(BadSequence);
+            }
+            builds_on_foundation(top, &cards[0])
+        },
+        Role::Tableau(_) => builds_on_tableau(top, &cards[0]),
+    };
+    if ok { Ok(()) } else { Err(NoBuild) }
+}}
+
+/*----------------------------------------------------------------------
+Enumerate all legal moves
+
+The order is fixed, so the same layout always gives the same list:
+by source pile, then by number of cards, then by target pile.  The
+stock move, a draw or a recycle, comes first.
+
+An ace moved from one empty foundation to another is legal, but only
+swaps two foundations, so it is left out of the list.
+*/
+
+impl Rules {
+pub fn legal_moves(&self, lo:&Layout, recycles:usize) -> Vec<Move> {
+    let mut moves:Vec<Move> = vec![];
+    for mv in [self.draw_move(lo), self.recycle_move(lo)] {
+        if self.check(lo, recycles, &mv).is_ok() {
+            moves.push(mv);
+        }
+    }
+    for from in 0..N_PILES {
+        let src = lo.pile(from);
+        let nmax = match role(from) {
+            Role::Stock => 0,
+            Role::Waste | Role::Foundation(_) => src.len().min(1),
+            Role::Tableau(_) => src.iter().rev()
+                .take_while(|c| c.face_up())
+                .count(),
+        };
+        for n in 1..=nmax {
+            // a longer tail is no more a sequence than this one
+            if !is_built_sequence(&src[src.len()-n..]) {
+                break;
+            }
+            for to in FOUNDATION..N_PILES {
+                if matches!((role(from), role(to)),
+                    (Role::Foundation(_), Role::Foundation(_))) {
+                    continue;
+                }
+                let mv = Move { from, to, n };
+                if self.check(lo, recycles, &mv).is_ok() {
+                    moves.push(mv);
+                }
+            }
+        }
+    }
+    moves
+}}
+
+/*----------------------------------------------------------------------
+Apply a move
+
+The move is checked first and the layout is not changed if it is not
+legal.  After cards leave a tableau pile, a newly exposed face down card
+is turned face up.  The caller should count a successful recycle.
+*/
+
+impl Rules {
+pub fn apply(&self, lo:&mut Layout, recycles:usize, mv:&Move)
+    -> Result<(), MoveError>
+{
+    self.check(lo, recycles, mv)?;
+    lo.move_tail(mv.from, mv.to, mv.n);
+    if mv.is_draw() {
+        lo.flip_tail(WASTE, mv.n);
+    }
+    if mv.is_recycle() {
+        lo.flip_pile(STOCK);
+    }
+    if let Role::Tableau(_) = role(mv.from) {
+        if let Some(c) = lo.pile(mv.from).last() {
+            if !c.face_up() {
+                lo.set_tail_face_up(mv.from, 1, true);
+            }
+        }
+    }
+    Ok(())
+}}
+
+/*----------------------------------------------------------------------
+Safe moves
+
+A card is safe to put on its foundation when the cards of the other
+color one rank lower, and of the other suit of its color two ranks
+lower, are on the foundations already.  The only cards that could be
+built on it are of the other color one rank lower, which are home.
+Those could come back down to the tableau, but only to hold a card of
+its color two ranks lower, which is home too.  So nothing left could
+need it, and the move never turns a game that can be won into one that
+cannot.  Any weaker test can, see test_solve_not_safe in solver.rs, so
+only these moves are played without asking.  An ace always is safe, and
+a two when the aces of the other color are home.  Not from the waste
+when drawing more than one, since that would change which cards later
+draws turn up.
+
+With auto_move on, the game plays safe moves by itself after each move
+of the player, see History::auto_move.  The solver always plays them
+first, whatever the rules say.
+*/
+
+impl Rules {
+pub fn is_safe(&self, lo:&Layout, mv:&Move) -> bool {
+    match (role(mv.from), role(mv.to)) {
+        (Role::Tableau(_), Role::Foundation(_)) => {},
+        (Role::Waste, Role::Foundation(_)) if 1 == self.draw => {},
+        _ => return false,
+    }
+    let Some(card) = lo.pile(mv.from).last() else { return false };
+    // cards home in each suit, one foundation each
+    let mut home = [0;N_FOUNDATIONS];
+    for f in FOUNDATION..TABLEAU {
+        if let Some(base) = lo.pile(f).first() {
+            home[base.suit() as usize] = lo.pile(f).len();
+        }
+    }
+    let rank = card.rank() as usize;
+    (0..N_FOUNDATIONS).filter(|s| *s != card.suit() as usize).all(|s| {
+        let other = Card::pack(&CardUnpacked { suit: s as u8, ..Default::default() });
+        match other.same_color(card) {
+            false => home[s] >= rank,
+            true => home[s] + 1 >= rank,
+        }
+    })
+}}
+
+// the first legal safe move, waste then tableau from the left
+impl Rules {
+pub fn safe_move(&self, lo:&Layout) -> Option<Move> {
+    std::iter::once(WASTE).chain(TABLEAU..N_PILES)
+        .flat_map(|from| (FOUNDATION..TABLEAU).map(move |to| Move { from, to, n: 1 }))
+        .find(|mv| self.check(lo, 0, mv).is_ok() && self.is_safe(lo, mv))
+}}
+
+/*----------------------------------------------------------------------
+Move notation
+
+Piles are named for people, counting from 1:
+    S  stock     W  waste     F1..F4  foundations     T1..T7  tableaux
+
+Terse form, for typing, logging and replay:
+    T1-3>T2     three cards from tableau 1 to tableau 2
+    W>F1        one card, the count is left out when it is 1
+    D3          draw 3 cards from stock to waste
+    R24         recycle, turn the 24 card waste over onto the stock
+
+Move::to_text always writes the counts for D and R, so the text alone
+gives back the same move.  Move::from_text reads this form, in either
+case.  Rules::parse_move is for a prompt.  It also takes a bare "D" or
+"R", and "T1>T2" with the count left out, and fills in the count from
+the layout.  Then it checks that the move is legal.
+
+Verbose form, for people, from Rules::describe:
+    move Three of Hearts onto Four of Clubs
+    move Ace of Spades to foundation 1
+    move King of Hearts and 4 cards on it to empty tableau 3
+    draw 3 cards from stock
+    turn waste over onto stock
+*/
+
+pub fn pile_name(ipile:usize) -> String {
+    match role(ipile) {
+        Role::Stock => "S".to_string(),
+        Role::Waste => "W".to_string(),
+        Role::Foundation(i) => format!("F{}", i + 1),
+        Role::Tableau(i) => format!("T{}", i + 1),
+    }
+}
+
+pub fn pile_from_name(name:&str) -> Option<usize> {
+    let name = name.to_ascii_uppercase();
+    let numbered = |prefix:&str, first:usize, n:usize| -> Option<usize> {
+        let i:usize = name.strip_prefix(prefix)?.parse().ok()?;
+        if (1..=n).contains(&i) { Some(first + i - 1) } else { None }
+    };
+    match name.as_str() {
+        "S" => Some(STOCK),
+        "W" => Some(WASTE),
+        _ => numbered("F", FOUNDATION, N_FOUNDATIONS)
+            .or_else(|| numbered("T", TABLEAU, N_TABLEAUX)),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveTextError {
+    Empty,
+    Syntax(String),             // text is not a move
+    BadPile(String),            // not a pile name
+    BadCount(String),           // not a number, or zero
+    NoMatch,                    // no legal move fits the text
+    Ambiguous,                  // more than one legal move fits
+                                // (cannot happen with klondike rules)
+    Illegal(MoveError),         // well formed, but against the rules
+}
+
+impl std::fmt::Display for MoveTextError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use MoveTextError::*;
+    match self {
+        Empty       => write!(f, "no move given"),
+        Syntax(s)   => write!(f, "\"{}\" is not a move, try T1-3>T2, W>F1, D or R", s),
+        BadPile(s)  => write!(f, "\"{}\" is not a pile, use S W F1-F4 T1-T7", s),
+        BadCount(s) => write!(f, "\"{}\" is not a count of cards", s),
+        NoMatch     => write!(f, "no legal move like that"),
+        Ambiguous   => write!(f, "more than one move like that, give a count"),
+        Illegal(e)  => write!(f, "illegal move: {}", e),
+    }
+}}
+
+impl std::error::Error for MoveTextError {}
+
+impl Move {
+pub fn to_text(&self) -> String {
+    if self.is_draw() {
+        format!("D{}", self.n)
+    } else if self.is_recycle() {
+        format!("R{}", self.n)
+    } else if 1 == self.n {
+        format!("{}>{}", pile_name(self.from), pile_name(self.to))
+    } else {
+        format!("{}-{}>{}", pile_name(self.from), self.n, pile_name(self.to))
+    }
+}}
+
+// count after a token, None if missing
+fn parse_count(s:&str) -> Result<Option<usize>, MoveTextError> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    match s.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(Some(n)),
+        _ => Err(MoveTextError::BadCount(s.to_string())),
+    }
+}
+
+// like from_text, but counts may be missing
+fn parse_loose(text:&str) -> Result<(usize, usize, Option<usize>), MoveTextError> {
+    use MoveTextError::*;
+    let t:String = text.split_whitespace().collect::<String>().to_ascii_uppercase();
+    if t.is_empty() {
+        return Err(Empty);
+    }
+    if let Some(rest) = t.strip_prefix('D') {
+        return Ok((STOCK, WASTE, parse_count(rest)?));
+    }
+    if let Some(rest) = t.strip_prefix('R') {
+        return Ok((WASTE, STOCK, parse_count(rest)?));
+    }
+    let Some((src, dst)) = t.split_once('>') else {
+        return Err(Syntax(text.to_string()));
+    };
+    let (src, n) = match src.split_once('-') {
+        Some((src, n)) if !n.is_empty() => (src, parse_count(n)?),
+        Some(_) => return Err(BadCount(String::new())),
+        None => (src, None),
+    };
+    let from = pile_from_name(src).ok_or_else(|| BadPile(src.to_string()))?;
+    let to = pile_from_name(dst).ok_or_else(|| BadPile(dst.to_string()))?;
+    if from == STOCK || to == STOCK {
+        return Err(Syntax(text.to_string()));
+    }
+    Ok((from, to, n))
+}
+
+impl Move {
+pub fn from_text(text:&str) -> Result<Move, MoveTextError> {
+    let (from, to, n) = parse_loose(text)?;
+    let n = match n {
+        Some(n) => n,
+        None if from == STOCK || to == STOCK =>
+            return Err(MoveTextError::BadCount(String::new())),
+        None => 1,
+    };
+    Ok(Move { from, to, n })
+}}
+
+impl Rules {
+pub fn parse_move(&self, lo:&Layout, recycles:usize, text:&str)
+    -> Result<Move, MoveTextError>
+{
+    let (from, to, n) = parse_loose(text)?;
+    let mv = match n {
+        Some(n) => Move { from, to, n },
+        None => {
+            let fits:Vec<Move> = self.legal_moves(lo, recycles).into_iter()
+                .filter(|mv| mv.from == from && mv.to == to)
+                .collect();
+            match fits.len() {
+                0 => return Err(MoveTextError::NoMatch),
+                1 => fits[0],
+                _ => return Err(MoveTextError::Ambiguous),
+            }
+        },
+    };
+    self.check(lo, recycles, &mv).map_err(MoveTextError::Illegal)?;
+    Ok(mv)
+}}
+
+// english for a count of cards
+fn cards(n:usize) -> String {
+    if 1 == n { "1 card".to_string() } else { format!("{} cards", n) }
+}
+
+impl Rules {
+pub fn describe(&self, lo:&Layout, mv:&Move) -> String {
+    if mv.is_draw() {
+        return format!("draw {} from stock", cards(mv.n));
+    }
+    if mv.is_recycle() {
+        return "turn waste over onto stock".to_string();
+    }
+    let src = lo.pile(mv.from);
+    let first = &src[src.len() - mv.n];
+    let mut out = format!("move {}", first.name());
+    if mv.n > 1 {
+        out += &format!(" and {} on it", cards(mv.n - 1));
+    }
+    match (role(mv.to), lo.pile(mv.to).last()) {
+        (Role::Foundation(i), _) => out += &format!(" to foundation {}", i + 1),
+        (Role::Tableau(i), None) => out += &format!(" to empty tableau {}", i + 1),
+        (_, Some(top)) => out += &format!(" onto {}", top.name()),
+        (_, None) => out += &format!(" to {}", pile_name(mv.to)),
+    }
+    out
+}}
+
+/*----------------------------------------------------------------------
+Symmetric piles
+
+Any suit may start any foundation, and every tableau column follows the
+same rules, so the four foundations are alike and so are the seven
+columns.  Two layouts that differ only by swapping foundations, or by
+swapping columns, play the same.  A search that sorts them into one
+canonical layout, see Layout::canonical_order, need only look at each
+once.
+
+Canonical gives the sorted layout and the real pile for each of its
+piles, so a move found on the canonical layout can be reported, or
+played, on the real one.  A move naming a pile that is not there, such
+as one read from text, maps to None.
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Canonical {
+    pub layout : Layout,
+    pub real   : Vec<usize>,            // real pile for each canonical pile
+}
+
+impl Rules {
+pub fn symmetric_piles(&self) -> Vec<std::ops::Range<usize>> {
+    vec![FOUNDATION..TABLEAU, TABLEAU..N_PILES]
+}}
+
+impl Rules {
+pub fn canonical(&self, lo:&Layout) -> Canonical {
+    let real = lo.canonical_order(&self.symmetric_piles());
+    Canonical { layout: lo.permuted(&real), real }
+}}
+
+// hash of the symmetry class of the layout, the same for every layout
+// with the same canonical layout, see Layout::zobrist_alike.  It is
+// not the zobrist hash of the canonical layout.
+impl Rules {
+pub fn canonical_hash(&self, lo:&Layout) -> u64 {
+    lo.zobrist_alike(&self.symmetric_piles())
+}}
+
+impl Rules {
+pub fn canonical_packed(&self, lp:LayoutPacked) -> Result<LayoutPacked, PackError> {
+    self.canonical(&Layout::unpack(lp)?).layout.pack()
+}}
+
+impl Canonical {
+pub fn real_move(&self, mv:&Move) -> Option<Move> {
+    Some(Move { from: *self.real.get(mv.from)?, to: *self.real.get(mv.to)?, n: mv.n })
+}}
+
+impl Canonical {
+pub fn canonical_move(&self, mv:&Move) -> Option<Move> {
+    let place = |ipile| self.real.iter().position(|r| *r == ipile);
+    Some(Move { from: place(mv.from)?, to: place(mv.to)?, n: mv.n })
+}}
+
+/*----------------------------------------------------------------------
+Game is won when all cards are on the foundations
+*/
+
+impl Rules {
+pub fn is_won(&self, lo:&Layout) -> bool {
+    let n:usize = (FOUNDATION..TABLEAU).map(|i| lo.pile(i).len()).sum();
+    n == Card::info().n_cards
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+// make a card from suit and rank codes, see card.rs
+#[cfg(test)]
+fn c(suit:u8, rank:u8, face_up:bool) -> Card {
+    Card::pack(&CardUnpacked { suit, rank, face_up, ..Default::default() })
+}
+
+// empty klondike layout with some piles filled in
+#[cfg(test)]
+fn layout_with(fill:Vec<(usize, Vec<Card>)>) -> Layout {
+    let mut piles:Vec<Vec<Card>> = vec![vec![]; N_PILES];
+    for (i, cards) in fill {
+        piles[i] = cards;
+    }
+    Layout::new(piles)
+}
+
+#[test]
+fn test_deal() {
+    let rules = Rules::default();
+    let lo = rules.deal(&Deck::new());
+    assert_eq!(lo.pile(STOCK).len(), 24);
+    assert_eq!(lo.pile(WASTE).len(), 0);
+    for f in 0..N_FOUNDATIONS {
+        assert_eq!(lo.pile(FOUNDATION + f).len(), 0);
+    }
+    for t in 0..N_TABLEAUX {
+        let p = lo.pile(TABLEAU + t);
+        assert_eq!(p.len(), t + 1);
+        assert!(p.last().unwrap().face_up());
+        assert!(p[..t].iter().all(|c| !c.face_up()));
+    }
+    assert!(lo.pile(STOCK).iter().all(|c| !c.face_up()));
+    // first row deals the first seven cards of the deck
+    let deck = Deck::new();
+    for t in 0..N_TABLEAUX {
+        let mut first = lo.pile(TABLEAU + t)[0];
+        first.set_face_up(false);
+        assert_eq!(first.code, deck.cards[t]);
+    }
+    assert!(lo.pack().is_ok());
+}
+
+#[test]
+fn test_check() {
+    use MoveError::*;
+    // spade=0 diamond=1 club=2 heart=3, ace=0 .. king=12
+    let lo = layout_with(vec![
+        (WASTE,       vec![c(1,0,true)]),                       // AD
+        (FOUNDATION,  vec![c(0,0,true)]),                       // AS
+        (TABLEAU,     vec![c(2,4,false), c(3,5,true), c(0,4,true)]), // 6H 5S
+        (TABLEAU+1,   vec![c(2,6,true)]),                       // 7C
+        (TABLEAU+2,   vec![c(0,1,true)]),                       // 2S
+        (TABLEAU+3,   vec![c(1,12,true)]),                      // KD
+    ]);
+    let rules = Rules::default();
+    let t = |from, to, n, r:Result<(), MoveError>| {
+        assert_eq!(rules.check(&lo, 0, &Move { from, to, n }), r);
+    };
+    t(WASTE,      FOUNDATION+1, 1, Ok(()));          // AD to empty
+    t(WASTE,      FOUNDATION,   1, Err(NoBuild));    // AD on AS
+    t(TABLEAU+2,  FOUNDATION,   1, Ok(()));          // 2S on AS
+    t(TABLEAU,    TABLEAU+1,    2, Ok(()));          // 6H 5S on 7C
+    t(TABLEAU,    TABLEAU+1,    1, Err(NoBuild));    // 5S on 7C
+    t(TABLEAU,    TABLEAU+1,    3, Err(FaceDown));
+    t(TABLEAU,    TABLEAU+1,    4, Err(NotEnoughCards));
+    t(TABLEAU+3,  TABLEAU+4,    1, Ok(()));          // KD to empty
+    t(TABLEAU+1,  TABLEAU+4,    1, Err(NoBuild));    // 7C to empty
+    t(TABLEAU,    WASTE,        1, Err(BadTarget));
+    t(TABLEAU,    TABLEAU,      1, Err(SamePile));
+    t(TABLEAU,    N_PILES,      1, Err(NoSuchPile));
+    t(FOUNDATION, TABLEAU+3,    1, Err(NoBuild));    // AS on KD
+}
+
+#[test]
+fn test_legal_moves_and_apply() {
+    let lo = layout_with(vec![
+        (WASTE,       vec![c(1,0,true)]),                       // AD
+        (TABLEAU,     vec![c(2,4,false), c(3,5,true), c(0,4,true)]), // 6H 5S
+        (TABLEAU+1,   vec![c(2,6,true)]),                       // 7C
+    ]);
+    let rules = Rules::default();
+    let moves = rules.legal_moves(&lo, 0);
+    // recycle the waste, AD to any of the four empty foundations,
+    // 6H 5S onto 7C
+    assert_eq!(moves.len(), 6);
+    assert!(moves[0].is_recycle());
+    assert!(moves.iter().all(|mv| rules.check(&lo, 0, mv).is_ok()));
+    let mv = Move { from:TABLEAU, to:TABLEAU+1, n:2 };
+    assert!(moves.contains(&mv));
+
+    // moving 6H 5S exposes the face down card, which is turned over
+    let mut lo2 = lo.clone();
+    rules.apply(&mut lo2, 0, &mv).unwrap();
+    assert_eq!(lo2.pile(TABLEAU), &[c(2,4,true)]);
+    assert_eq!(lo2.pile(TABLEAU+1), &[c(2,6,true), c(3,5,true), c(0,4,true)]);
+
+    // illegal move leaves layout unchanged
+    let mut lo3 = lo.clone();
+    let bad = Move { from:TABLEAU+1, to:TABLEAU, n:1 };
+    assert!(rules.apply(&mut lo3, 0, &bad).is_err());
+    assert_eq!(lo3, lo);
+
+    // an ace on a foundation is not offered to the other empty ones
+    let lo4 = layout_with(vec![(FOUNDATION, vec![c(1,0,true)])]);
+    let shuffle = Move { from:FOUNDATION, to:FOUNDATION+1, n:1 };
+    assert!(rules.check(&lo4, 0, &shuffle).is_ok());
+    assert!(!rules.legal_moves(&lo4, 0).contains(&shuffle));
+}
+
+#[test]
+fn test_safe_move() {
+    // spade=0 diamond=1 club=2 heart=3, ace=0 .. king=12
+    let lo = layout_with(vec![
+        (WASTE,        vec![c(3,1,true)]),                      // 2H
+        (FOUNDATION,   vec![c(0,0,true), c(0,1,true)]),         // AS 2S
+        (FOUNDATION+1, vec![c(1,0,true)]),                      // AD
+        (FOUNDATION+2, vec![c(2,0,true)]),                      // AC
+        (FOUNDATION+3, vec![c(3,0,true)]),                      // AH
+        (TABLEAU,      vec![c(2,8,false), c(1,1,true)]),        // 2D
+        (TABLEAU+1,    vec![c(0,2,true)]),                      // 3S
+    ]);
+    let rules = Rules::default();
+    let d2 = Move { from:TABLEAU, to:FOUNDATION+1, n:1 };
+    let h2 = Move { from:WASTE, to:FOUNDATION+3, n:1 };
+    let s3 = Move { from:TABLEAU+1, to:FOUNDATION, n:1 };
+    assert!(rules.is_safe(&lo, &d2));
+    // from the waste drawing three
+    assert!(rules.check(&lo, 0, &h2).is_ok() && !rules.is_safe(&lo, &h2));
+    // the red twos could still want it
+    assert!(rules.check(&lo, 0, &s3).is_ok() && !rules.is_safe(&lo, &s3));
+    assert_eq!(rules.safe_move(&lo), Some(d2));
+    assert_eq!(Rules::draw1().safe_move(&lo), Some(h2));
+
+    // the other red suit two ranks lower must be home too, or the four of
+    // spades might have to come down onto the five of hearts to hold it
+    let high = |diamonds:u8| layout_with(vec![
+        (FOUNDATION,   (0..4).map(|r| c(0,r,true)).collect()),
+        (FOUNDATION+1, (0..diamonds).map(|r| c(1,r,true)).collect()),
+        (FOUNDATION+2, (0..4).map(|r| c(2,r,true)).collect()),
+        (FOUNDATION+3, (0..4).map(|r| c(3,r,true)).collect()),
+        (TABLEAU,      vec![c(3,4,true)]),                      // 5H
+    ]);
+    let h5 = Move { from:TABLEAU, to:FOUNDATION+3, n:1 };
+    assert!(!rules.is_safe(&high(1), &h5));
+    assert!(!rules.is_safe(&high(2), &h5));
+    assert!(rules.is_safe(&high(3), &h5));
+
+    // with a foundation not started, only aces
+    let lo = layout_with(vec![(TABLEAU, vec![c(1,1,true)]), (TABLEAU+1, vec![c(1,0,true)])]);
+    assert_eq!(rules.safe_move(&lo), Some(Move { from:TABLEAU+1, to:FOUNDATION, n:1 }));
+}
+
+#[test]
+fn test_is_won() {
+    let rules = Rules::default();
+    let mut fill = vec![];
+    for s in 0..4 {
+        fill.push((FOUNDATION + s as usize, (0..13).map(|r| c(s,r,true)).collect()));
+    }
+    assert!(rules.is_won(&layout_with(fill)));
+    assert!(!rules.is_won(&rules.deal(&Deck::new())));
+}
+
+#[test]
+fn test_draw_and_recycle() {
+    use MoveError::*;
+    // stock of 7 cards, top is the last one
+    let stock:Vec<Card> = (0..7).map(|r| c(0,r,false)).collect();
+    let lo = layout_with(vec![(STOCK, stock)]);
+
+    // draw 3, the top of the stock goes to the bottom of those drawn
+    let rules = Rules::default();
+    let mut lo1 = lo.clone();
+    let moves = rules.legal_moves(&lo1, 0);
+    assert_eq!(moves, vec![Move { from:STOCK, to:WASTE, n:3 }]);
+    rules.apply(&mut lo1, 0, &moves[0]).unwrap();
+    assert_eq!(lo1.pile(STOCK).len(), 4);
+    assert_eq!(lo1.pile(WASTE), &[c(0,6,true), c(0,5,true), c(0,4,true)]);
+    rules.apply(&mut lo1, 0, &moves[0]).unwrap();
+    // last draw takes only what is left
+    let short = Move { from:STOCK, to:WASTE, n:1 };
+    assert_eq!(rules.check(&lo1, 0, &moves[0]), Err(BadDraw));
+    rules.apply(&mut lo1, 0, &short).unwrap();
+    assert!(lo1.pile(STOCK).is_empty());
+    assert_eq!(rules.legal_moves(&lo1, 0)[0], Move { from:WASTE, to:STOCK, n:7 });
+
+    // recycle turns the waste over, giving back the original stock
+    let recycle = Move { from:WASTE, to:STOCK, n:7 };
+    rules.apply(&mut lo1, 0, &recycle).unwrap();
+    assert_eq!(lo1, lo);
+    assert_eq!(rules.check(&lo1, 0, &recycle), Err(StockNotEmpty));
+
+    // draw 1, one card at a time
+    let rules = Rules::draw1();
+    let mut lo2 = lo.clone();
+    let one = Move { from:STOCK, to:WASTE, n:1 };
+    for i in 0..7 {
+        rules.apply(&mut lo2, 0, &one).unwrap();
+        assert_eq!(lo2.pile(WASTE).last(), Some(&c(0,6-i,true)));
+    }
+    assert_eq!(rules.check(&lo2, 0, &one), Err(NotEnoughCards));
+
+    // pass limits
+    let t = |rules:Rules, recycles, r| {
+        assert_eq!(rules.check(&lo2, recycles, &recycle), r);
+    };
+    t(Rules::draw1(), 0, Ok(()));
+    t(Rules::draw1(), 1, Ok(()));
+    t(Rules::draw1(), 2, Err(NoPassesLeft));
+    t(Rules::vegas(), 0, Err(NoPassesLeft));
+    t(Rules::default(), 1000, Ok(()));
+}
+
+#[test]
+fn test_move_text() {
+    // terse form round trip
+    fn t(text:&str, from:usize, to:usize, n:usize) {
+        let mv = Move { from, to, n };
+        assert_eq!(mv.to_text(), text);
+        assert_eq!(Move::from_text(text), Ok(mv));
+        assert_eq!(Move::from_text(&text.to_lowercase()), Ok(mv));
+    }
+    t("T1-3>T2",  TABLEAU,     TABLEAU+1,    3);
+    t("W>F1",     WASTE,       FOUNDATION,   1);
+    t("F4>T7",    FOUNDATION+3, TABLEAU+6,   1);
+    t("T7-13>T1", TABLEAU+6,   TABLEAU,      13);
+    t("D3",       STOCK,       WASTE,        3);
+    t("D1",       STOCK,       WASTE,        1);
+    t("R24",      WASTE,       STOCK,        24);
+    for ipile in 0..N_PILES {
+        assert_eq!(pile_from_name(&pile_name(ipile)), Some(ipile));
+    }
+    assert_eq!(Move::from_text(" t1 - 3 > t2 "), Move::from_text("T1-3>T2"));
+
+    use MoveTextError::*;
+    fn e(text:&str, err:MoveTextError) {
+        assert_eq!(Move::from_text(text), Err(err));
+    }
+    e("",        Empty);
+    e("T1T2",    Syntax("T1T2".to_string()));
+    e("T8>T1",   BadPile("T8".to_string()));
+    e("T1>F0",   BadPile("F0".to_string()));
+    e("T1-x>T2", BadCount("X".to_string()));
+    e("T1-0>T2", BadCount("0".to_string()));
+    e("T1->T2",  BadCount(String::new()));
+    e("D",       BadCount(String::new()));
+    e("S>W",     Syntax("S>W".to_string()));
+}
+
+#[test]
+fn test_parse_and_describe() {
+    use MoveTextError::*;
+    let lo = layout_with(vec![
+        (STOCK,       vec![c(0,9,false), c(0,10,false)]),
+        (WASTE,       vec![c(1,0,true)]),                       // AD
+        (FOUNDATION,  vec![c(0,0,true)]),                       // AS
+        (TABLEAU,     vec![c(2,4,false), c(3,5,true), c(0,4,true)]), // 6H 5S
+        (TABLEAU+1,   vec![c(2,6,true)]),                       // 7C
+        (TABLEAU+2,   vec![c(3,12,true)]),                      // KH
+        (TABLEAU+3,   vec![c(0,1,true)]),                       // 2S
+    ]);
+    let rules = Rules::default();
+    let p = |text| rules.parse_move(&lo, 0, text);
+    let mv = |from, to, n| Move { from, to, n };
+
+    // counts filled in from the layout
+    assert_eq!(p("D"),     Ok(mv(STOCK, WASTE, 2)));
+    assert_eq!(p("T1>T2"), Ok(mv(TABLEAU, TABLEAU+1, 2)));
+    assert_eq!(p("T4>F1"), Ok(mv(TABLEAU+3, FOUNDATION, 1)));
+    assert_eq!(p("T1-2>T2"), Ok(mv(TABLEAU, TABLEAU+1, 2)));
+    assert_eq!(p("R"),     Err(NoMatch));
+    assert_eq!(p("T2>T1"), Err(NoMatch));
+    assert_eq!(p("T1-1>T2"), Err(Illegal(MoveError::NoBuild)));
+    assert_eq!(p("D3"),    Err(Illegal(MoveError::BadDraw)));
+
+    // verbose form
+    let d = |m:Move| rules.describe(&lo, &m);
+    assert_eq!(d(mv(TABLEAU, TABLEAU+1, 2)),
+        "move Six of Hearts and 1 card on it onto Seven of Clubs");
+    assert_eq!(d(mv(WASTE, FOUNDATION+1, 1)),
+        "move Ace of Diamonds to foundation 2");
+    assert_eq!(d(mv(TABLEAU+2, TABLEAU+4, 1)),
+        "move King of Hearts to empty tableau 5");
+    assert_eq!(d(mv(TABLEAU+3, FOUNDATION, 1)),
+        "move Two of Spades to foundation 1");
+    assert_eq!(d(mv(STOCK, WASTE, 2)), "draw 2 cards from stock");
+    assert_eq!(d(mv(WASTE, STOCK, 1)), "turn waste over onto stock");
+}
+
+#[test]
+fn test_canonical() {
+    use crate::misc::SplitMix64;
+    let rules = Rules::default();
+    let mut lo = rules.deal(&Deck::new());
+    let mut recycles = 0;
+    let mut rng = SplitMix64::new(5);
+    for _ in 0..200 {
+        let canon = rules.canonical(&lo);
+        assert_eq!(rules.canonical_hash(&canon.layout), rules.canonical_hash(&lo));
+        assert_eq!(rules.canonical(&canon.layout).layout, canon.layout);
+        assert_eq!(rules.canonical_packed(lo.pack().unwrap()),
+            canon.layout.pack());
+
+        // swapping alike piles does not change the canonical layout
+        let mut order:Vec<usize> = (0..N_PILES).collect();
+        order.swap(FOUNDATION, FOUNDATION + 3);
+        order.swap(TABLEAU + 1, TABLEAU + 6);
+        order.swap(TABLEAU, TABLEAU + 2);
+        let swapped = lo.permuted(&order);
+        assert_eq!(rules.canonical(&swapped).layout, canon.layout);
+        assert_eq!(rules.canonical_hash(&swapped), rules.canonical_hash(&lo));
+        // but swapping stock and waste does
+        order.swap(STOCK, WASTE);
+        if lo.pile(STOCK) != lo.pile(WASTE) {
+            assert_ne!(rules.canonical(&lo.permuted(&order)).layout, canon.layout);
+            assert_ne!(rules.canonical_hash(&lo.permuted(&order)), rules.canonical_hash(&lo));
+        }
+
+        // moves on the canonical layout map to the same moves on the real one
+        let moves = rules.legal_moves(&canon.layout, recycles);
+        if moves.is_empty() {
+            break;
+        }
+        for cmv in &moves {
+            let mv = canon.real_move(cmv).unwrap();
+            assert_eq!(canon.canonical_move(&mv), Some(*cmv));
+            let mut a = canon.layout.clone();
+            rules.apply(&mut a, recycles, cmv).unwrap();
+            let mut b = lo.clone();
+            rules.apply(&mut b, recycles, &mv).unwrap();
+            assert_eq!(rules.canonical(&a).layout, rules.canonical(&b).layout);
+            assert_eq!(rules.canonical_hash(&a), rules.canonical_hash(&b));
+        }
+        let cmv = moves[rng.below(moves.len() as u64) as usize];
+        let mv = canon.real_move(&cmv).unwrap();
+        rules.apply(&mut lo, recycles, &mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+    }
+
+    // a move naming a pile not there maps to nothing
+    let canon = rules.canonical(&lo);
+    let bad = Move { from: N_PILES, to: TABLEAU, n: 1 };
+    assert_eq!(canon.real_move(&bad), None);
+    assert_eq!(canon.canonical_move(&bad), None);
+    let bad = Move { from: TABLEAU, to: 99, n: 1 };
+    assert_eq!(canon.real_move(&bad), None);
+    assert_eq!(canon.canonical_move(&bad), None);
+}
+
+// end mod klondike ----------------------------------------------------
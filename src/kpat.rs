@@ -0,0 +1,524 @@
+/*----------------------------------------------------------------------
+Kpat module
+
+Read and write xml game state files from the KDE game "kpat" which is
+a collection of "patience" games.  Replay them into layouts, and make
+them from a sequence of our layouts.
+
+The goal is to be able to play parallel games with kpat and compare
+game state.  This is needed for testing the details of the stock and
+waste, which are difficult to observe from the UI.
+
+Kpat seems to use european nomenclature for the games.  I guess this
+reflects the location of most of the KDE developers. So far I have
+found these:
+  patience  <-->  solitaire
+     talon  <-->  stock
+      play  <-->  tableau
+    target  <-->  foundation
+      pile  <-->  waste
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+/*----------------------------------------------------------------------
+Key excerpts of kpat xml file, sufficient to show all tags, attributes
+and nesting.
+
+Game is a header followed by a list of states, one marked current.
+States are a list of moves.
+Moves are a list of cards.
+
+The deal-number is a deep mystery and can remain so.  It does not
+contain any useful information for us.
+
+The card-id turns out to be simple.  It is 7 decimal digits of
+    (index << 16) | (suit << 8) | rank
+where rank is 1..13 with ace 1, suit is clubs 0, diamonds 1, hearts 2,
+spades 3, and index is 4*(rank-1) + suit.  All 52 cards in the sample
+files follow this, so we write ids the same way.
+
+    <?xml version="1.0" encoding="UTF-8"?>
+    <kpat-game game-type="klondike" game-type-options="1" deal-number="88783540">
+      <state>
+        <move pile="talon" position="0">
+          <card id="1966600" suit="hearts" rank="eight" turn="face-down"/>
+          <card id="0655875" suit="hearts" rank="
+        </move>
+        <move pile="play0" position="0">
+          <card id="1704455" suit="hearts" rank="seven" turn="face-up"/>
+        </move>
+      </state>
+      <state current="true">
+        <move pile="pile" position="4">
+          <card id="0262146" suit="clubs" rank="two" turn="face-up"/>
+        </move>
+      </state>
+    </kpat-game>
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+// the yaserde derive macros expand to impl blocks inside a const
+#![allow(non_local_definitions)]
+
+use yaserde_derive::{YaDeserialize, YaSerialize};
+
+use crate::card::*;
+use crate::klondike::*;
+use crate::layout::*;
+
+#[derive(Default, PartialEq, Debug, YaDeserialize, YaSerialize, Clone)]
+#[yaserde(root = "kpat-game")]
+pub struct KpatGame {
+    #[yaserde(attribute, rename = "game-type")]
+    pub game_type : String,
+    #[yaserde(attribute, rename = "game-type-options")]
+    pub game_type_options : u32,
+    #[yaserde(attribute, rename = "deal-number")]
+    pub deal_number : u32,
+    #[yaserde(rename = "state")]
+    pub states : Vec<KpatState>,
+}
+
+#[derive(Default, PartialEq, Debug, YaDeserialize, YaSerialize, Clone)]
+pub struct KpatState {
+    #[yaserde(attribute, skip_serializing_if = "not_current")]
+    pub current : bool,
+    #[yaserde(rename = "move")]
+    pub moves : Vec<KpatMove>,
+}
+
+#[derive(Default, PartialEq, Debug, YaDeserialize, YaSerialize, Clone)]
+pub struct KpatMove {
+    #[yaserde(attribute)]
+    pub pile : String,
+    #[yaserde(attribute)]
+    pub position : u32,
+    #[yaserde(rename = "card")]
+    pub cards : Vec<KpatCard>,
+}
+
+#[derive(Default, PartialEq, Debug, YaDeserialize, YaSerialize, Clone)]
+pub struct KpatCard {
+    #[yaserde(attribute)]
+    pub id : String,
+    #[yaserde(attribute)]
+    pub suit : String,
+    #[yaserde(attribute)]
+    pub rank : String,
+    #[yaserde(attribute, skip_serializing_if = "no_turn")]
+    pub turn : String,
+}
+
+// kpat leaves out these attributes rather than write them empty
+impl KpatState {
+fn not_current(&self, current:&bool) -> bool {
+    !*current
+}}
+
+impl KpatCard {
+fn no_turn(&self, turn:&str) -> bool {
+    turn.is_empty()
+}}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KpatError {
+    Io(String),                                 // could not read file
+    Xml(String),                                // could not parse xml
+    UnknownPile(String),
+    UnknownSuit(String),
+    UnknownRank(String),
+    UnknownTurn(String),
+    BadPosition { pile:String, position:u32 },  // past end of pile
+}
+
+impl std::fmt::Display for KpatError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use KpatError::*;
+    match self {
+        Io(s)          => write!(f, "kpat file: {}", s),
+        Xml(s)         => write!(f, "kpat xml: {}", s),
+        UnknownPile(s) => write!(f, "kpat pile name unknown: \"{}\"", s),
+        UnknownSuit(s) => write!(f, "kpat suit name unknown: \"{}\"", s),
+        UnknownRank(s) => write!(f, "kpat rank name unknown: \"{}\"", s),
+        UnknownTurn(s) => write!(f, "kpat card turn unknown: \"{}\"", s),
+        BadPosition { pile, position } =>
+            write!(f, "kpat position {} is past end of pile {}", position, pile),
+    }
+}}
+
+impl std::error::Error for KpatError {}
+
+/*----------------------------------------------------------------------
+Load and parse
+*/
+
+pub fn parse_kpat_xml(content:&str) -> Result<KpatGame, KpatError> {
+    yaserde::de::from_str(content).map_err(KpatError::Xml)
+}
+
+pub fn load_kpat_xml(path:&str) -> Result<KpatGame, KpatError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| KpatError::Io(format!("{}: {}", path, e)))?;
+    parse_kpat_xml(&content)
+}
+
+/*----------------------------------------------------------------------
+Translate kpat names to ours
+
+See the table of pile indices in klondike.rs
+*/
+
+pub fn pile_from_kpat(name:&str) -> Result<usize, KpatError> {
+    let numbered = |prefix:&str, first:usize, n:usize| -> Option<usize> {
+        let i:usize = name.strip_prefix(prefix)?.parse().ok()?;
+        if i < n { Some(first + i) } else { None }
+    };
+    match name {
+        "talon" => Some(STOCK),
+        "pile"  => Some(WASTE),
+        _ => numbered("target", FOUNDATION, N_FOUNDATIONS)
+            .or_else(|| numbered("play", TABLEAU, N_TABLEAUX)),
+    }.ok_or_else(|| KpatError::UnknownPile(name.to_string()))
+}
+
+// cards moved to a foundation have no turn attribute, they are face up
+pub fn card_from_kpat(kc:&KpatCard) -> Result<Card, KpatError> {
+    let suit = suit_from_name(&kc.suit)
+        .ok_or_else(|| KpatError::UnknownSuit(kc.suit.clone()))?;
+    let rank = rank_from_name(&kc.rank)
+        .ok_or_else(|| KpatError::UnknownRank(kc.rank.clone()))?;
+    let face_up = match kc.turn.as_str() {
+        "face-up" | "" => true,
+        "face-down"    => false,
+        _ => return Err(KpatError::UnknownTurn(kc.turn.clone())),
+    };
+    Ok(Card::pack(&CardUnpacked { suit, rank, face_up, ..Default::default() }))
+}
+
+// inverse of pile_from_kpat
+pub fn pile_to_kpat(ipile:usize) -> String {
+    match role(ipile) {
+        Role::Stock => "talon".to_string(),
+        Role::Waste => "pile".to_string(),
+        Role::Foundation(i) => format!("target{}", i),
+        Role::Tableau(i) => format!("play{}", i),
+    }
+}
+
+// inverse of card_from_kpat, foundation cards have no turn
+pub fn card_to_kpat(card:&Card, ipile:usize) -> KpatCard {
+    // kpat suit order is clubs, diamonds, hearts, spades
+    const KPAT_SUIT:[u32;4] = [3, 1, 0, 2];
+    let suit = KPAT_SUIT[card.suit() as usize];
+    let rank = card.rank() as u32 + 1;
+    let index = 4*(rank-1) + suit;
+    let turn = match (role(ipile), card.face_up()) {
+        (Role::Foundation(_), _) => "",
+        (_, true)  => "face-up",
+        (_, false) => "face-down",
+    };
+    KpatCard {
+        id   : format!("{:07}", (index << 16) | (suit << 8) | rank),
+        suit : suit_name(card.suit()).to_lowercase(),
+        rank : rank_name(card.rank()).to_lowercase(),
+        turn : turn.to_string(),
+    }
+}
+
+#[test]
+fn test_names() {
+    assert_eq!(pile_from_kpat("talon"),   Ok(STOCK));
+    assert_eq!(pile_from_kpat("pile"),    Ok(WASTE));
+    assert_eq!(pile_from_kpat("target0"), Ok(FOUNDATION));
+    assert_eq!(pile_from_kpat("target3"), Ok(FOUNDATION + 3));
+    assert_eq!(pile_from_kpat("play0"),   Ok(TABLEAU));
+    assert_eq!(pile_from_kpat("play6"),   Ok(TABLEAU + 6));
+    assert!(pile_from_kpat("play7").is_err());
+    assert!(pile_from_kpat("target").is_err());
+    assert!(pile_from_kpat("stock").is_err());
+
+    for i in 0..N_PILES {
+        assert_eq!(pile_from_kpat(&pile_to_kpat(i)), Ok(i));
+    }
+
+    let kc = |suit:&str, rank:&str, turn:&str| KpatCard {
+        suit:suit.to_string(), rank:rank.to_string(), turn:turn.to_string(),
+        ..Default::default()
+    };
+    let c = card_from_kpat(&kc("hearts", "queen", "face-down")).unwrap();
+    assert_eq!((c.suit(), c.rank(), c.face_up()), (3, 11, false));
+    let c = card_from_kpat(&kc("spades", "ace", "")).unwrap();
+    assert_eq!((c.suit(), c.rank(), c.face_up()), (0, 0, true));
+    assert!(card_from_kpat(&kc("stars", "ace", "")).is_err());
+    assert!(card_from_kpat(&kc("spades", "one", "")).is_err());
+    assert!(card_from_kpat(&kc("spades", "ace", "sideways")).is_err());
+
+    // ids as seen in the sample files
+    let c = card_from_kpat(&kc("hearts", "queen", "face-down")).unwrap();
+    assert_eq!(card_to_kpat(&c, STOCK),
+        KpatCard { id:"3015180".to_string(), ..kc("hearts", "queen", "face-down") });
+    let c = card_from_kpat(&kc("clubs", "ace", "")).unwrap();
+    assert_eq!(card_to_kpat(&c, FOUNDATION),
+        KpatCard { id:"0000001".to_string(), ..kc("clubs", "ace", "") });
+}
+
+/*----------------------------------------------------------------------
+Replay
+
+States in the file are incremental.  Each move names a pile and a
+position in it, followed by cards.  Each card is taken from wherever it
+is now, and put in the named pile, starting at that position.  A card
+turned over in place appears as a move to its own position.  Cards not
+mentioned stay where they are.  So to get to any state, every state
+before it must be applied in turn.
+
+The result holds the layout after each state, and the index of the one
+marked current.  If none is marked, the last one is current.
+*/
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KpatReplay {
+    pub layouts : Vec<Layout>,
+    pub current : usize,
+}
+
+// klondike rules matching the kpat game options
+impl KpatGame {
+pub fn rules(&self) -> Rules {
+    let draw = if 3 == self.game_type_options { 3 } else { 1 };
+    Rules::new(draw, Passes::Unlimited)
+}}
+
+// same card, ignoring face up
+fn same_card(a:&Card, b:&Card) -> bool {
+    a.suit() == b.suit() && a.rank() == b.rank()
+}
+
+fn apply_kpat_move(piles:&mut [Vec<Card>], km:&KpatMove)
+    -> Result<(), KpatError>
+{
+    let ipile = pile_from_kpat(&km.pile)?;
+    for (i, kc) in km.cards.iter().enumerate() {
+        let card = card_from_kpat(kc)?;
+        // take it from where it is now
+        for pile in piles.iter_mut() {
+            if let Some(j) = pile.iter().position(|c| same_card(c, &card)) {
+                pile.remove(j);
+                break;
+            }
+        }
+        let pos = km.position as usize + i;
+        let pile = &mut piles[ipile];
+        if pos > pile.len() {
+            return Err(KpatError::BadPosition {
+                pile:km.pile.clone(), position:pos as u32 });
+        }
+        pile.insert(pos, card);
+    }
+    Ok(())
+}
+
+impl KpatGame {
+pub fn replay(&self) -> Result<KpatReplay, KpatError> {
+    let mut piles:Vec<Vec<Card>> = vec![vec![]; N_PILES];
+    let mut replay = KpatReplay::default();
+    for (i, ks) in self.states.iter().enumerate() {
+        for km in &ks.moves {
+            apply_kpat_move(&mut piles, km)?;
+        }
+        replay.layouts.push(Layout::new(piles.clone()));
+        if ks.current {
+            replay.current = i;
+        }
+    }
+    if !self.states.iter().any(|ks| ks.current) {
+        replay.current = replay.layouts.len().saturating_sub(1);
+    }
+    Ok(replay)
+}}
+
+/*----------------------------------------------------------------------
+Differential test against kpat
+
+Each state in the sample files is one player move.  Check that our
+rules engine, given the layout before, can reach the layout after with
+exactly one legal move.  This exercises the stock and waste handling.
+*/
+
+#[test]
+fn test_replay_samples() {
+    for i in 1..=4 {
+        let path = format!("./data/{}.kpat.save.xml", i);
+        let kg = load_kpat_xml(&path).unwrap();
+        let replay = kg.replay().unwrap();
+        assert_eq!(replay.layouts.len(), kg.states.len());
+        assert_eq!(replay.current, replay.layouts.len() - 1);
+
+        // first state is a fresh deal
+        let lo = &replay.layouts[0];
+        assert_eq!(lo.pile(STOCK).len(), 24);
+        for t in 0..N_TABLEAUX {
+            assert_eq!(lo.pile(TABLEAU + t).len(), t + 1);
+        }
+
+        // every layout holds the whole deck
+        for lo in &replay.layouts {
+            let n:usize = lo.piles().iter().map(|p| p.len()).sum();
+            assert_eq!(n, Card::info().n_cards);
+            assert!(lo.pack().is_ok());
+        }
+
+        // each later state is one legal move
+        let rules = kg.rules();
+        for w in replay.layouts.windows(2) {
+            let found = rules.legal_moves(&w[0], 0).iter().any(|mv| {
+                let mut lo = w[0].clone();
+                rules.apply(&mut lo, 0, mv).is_ok() && lo == w[1]
+            });
+            assert!(found, "{}: no legal move reaches next state", path);
+        }
+    }
+}
+
+#[test]
+fn test_replay_errors() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <kpat-game game-type="klondike" game-type-options="1" deal-number="1">
+          <state>
+            <move pile="play0" position="3">
+              <card id="1" suit="hearts" rank="seven" turn="face-up"/>
+            </move>
+          </state>
+        </kpat-game>"#;
+    let kg = parse_kpat_xml(xml).unwrap();
+    assert_eq!(kg.replay(), Err(KpatError::BadPosition {
+        pile:"play0".to_string(), position:3 }));
+    let bad = xml.replace("play0", "play9");
+    let kg = parse_kpat_xml(&bad).unwrap();
+    assert_eq!(kg.replay(), Err(KpatError::UnknownPile("play9".to_string())));
+    assert!(load_kpat_xml("./data/missing.xml").is_err());
+}
+
+/*----------------------------------------------------------------------
+Export
+
+Make a kpat game from a sequence of our layouts, the inverse of replay.
+The first state lists every card in every pile, as kpat does when it
+deals.  Each run of cards with the same turn in a pile is a separate
+move, like kpat, which lists face down cards before face up ones.
+
+Each later state lists only what changed.  For each pile that differs
+from the layout before, all its cards from the first difference to the
+end are listed.  Replay takes each card from wherever it was, so cards
+that left a pile without arriving at a changed one need no mention.
+
+game-type-options is the draw count of the rules.
+*/
+
+// kpat moves for the cards of pile ipile from position start onward
+fn kpat_moves(lo:&Layout, ipile:usize, start:usize) -> Vec<KpatMove> {
+    let pile = lo.pile(ipile);
+    let mut moves:Vec<KpatMove> = vec![];
+    let mut i = start;
+    while i < pile.len() {
+        let up = pile[i].face_up();
+        let n = pile[i..].iter().take_while(|c| c.face_up() == up).count();
+        moves.push(KpatMove {
+            pile     : pile_to_kpat(ipile),
+            position : i as u32,
+            cards    : pile[i..i+n].iter().map(|c| card_to_kpat(c, ipile)).collect(),
+        });
+        i += n;
+    }
+    moves
+}
+
+impl KpatGame {
+pub fn from_layouts(layouts:&[Layout], current:usize, rules:&Rules, deal_number:u32)
+    -> KpatGame
+{
+    let mut states:Vec<KpatState> = vec![];
+    for (i, lo) in layouts.iter().enumerate() {
+        let mut moves:Vec<KpatMove> = vec![];
+        for ipile in 0..N_PILES {
+            let start = if 0 == i { Some(0) } else {
+                let old = layouts[i-1].pile(ipile);
+                let new = lo.pile(ipile);
+                let same = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+                if same == new.len() { None } else { Some(same) }
+            };
+            if let Some(start) = start {
+                moves.extend(kpat_moves(lo, ipile, start));
+            }
+        }
+        states.push(KpatState { current: i == current, moves });
+    }
+    KpatGame {
+        game_type         : "klondike".to_string(),
+        game_type_options : rules.draw as u32,
+        deal_number,
+        states,
+    }
+}}
+
+impl KpatGame {
+pub fn to_xml(&self) -> Result<String, KpatError> {
+    let config = yaserde::ser::Config {
+        perform_indent             : true,
+        write_document_declaration : true,
+        indent_string              : Some("\t".to_string()),
+    };
+    yaserde::ser::to_string_with_config(self, &config).map_err(KpatError::Xml)
+}}
+
+pub fn save_kpat_xml(path:&str, kg:&KpatGame) -> Result<(), KpatError> {
+    let content = kg.to_xml()?;
+    std::fs::write(path, content)
+        .map_err(|e| KpatError::Io(format!("{}: {}", path, e)))
+}
+
+#[test]
+fn test_export_samples() {
+    for i in 1..=4 {
+        let path = format!("./data/{}.kpat.save.xml", i);
+        let kg = load_kpat_xml(&path).unwrap();
+        let replay = kg.replay().unwrap();
+        let rules = kg.rules();
+
+        // export gives back the very same kpat game
+        let kg2 = KpatGame::from_layouts(
+            &replay.layouts, replay.current, &rules, kg.deal_number);
+        assert_eq!(kg2, kg);
+
+        // and it survives a trip through xml
+        let xml = kg2.to_xml().unwrap();
+        let kg3 = parse_kpat_xml(&xml).unwrap();
+        assert_eq!(kg3, kg);
+        assert_eq!(kg3.replay().unwrap(), replay);
+    }
+}
+
+#[test]
+fn test_export_game() {
+    use crate::deck::Deck;
+    // play a few moves of our own, including a recycle
+    let rules = Rules::new(3, Passes::Unlimited);
+    let mut lo = rules.deal(&Deck::from_deal(7));
+    let mut layouts = vec![lo.clone()];
+    for _ in 0..12 {
+        let mv = rules.legal_moves(&lo, 0)[0];
+        rules.apply(&mut lo, 0, &mv).unwrap();
+        layouts.push(lo.clone());
+    }
+    let kg = KpatGame::from_layouts(&layouts, 5, &rules, 7);
+    assert_eq!(kg.game_type_options, 3);
+    let kg2 = parse_kpat_xml(&kg.to_xml().unwrap()).unwrap();
+    let replay = kg2.replay().unwrap();
+    assert_eq!(replay.layouts, layouts);
+    assert_eq!(replay.current, 5);
+}
+
+// end mod kpat --------------------------------------------------------
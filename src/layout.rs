@@ -11,6 +11,8 @@ representation consists of a single, compact, self contained block of
 memory.  All the data is in one place and there are no pointers or
 references to other data.  So it is suitable for use as a key in a hash
 table.  The unpacked representation allows for convenient manipulation.
+It also keeps a zobrist hash up to date as cards move, for lookup
+without packing.
 
 The layout does not contain sufficient information to properly display
 the cards.  For example, some piles may be displayed in a spread out
@@ -28,6 +30,7 @@ SPDX-License-Identifier: AGPL-3.0-or-later
 #![allow(unused_variables)]
 
 use crate::card::*;
+use crate::misc::SplitMix64;
 //use fixedstr::fstr;
 
 
@@ -37,22 +40,156 @@ cache friendly boundary.
 */
 pub const PACK_SIZE:usize = 64;
 
-#[derive(Clone, Debug, Default)]
+/*----------------------------------------------------------------------
+Packed format
+
+Each pile is written as a pile marker (see card.rs) holding the count
+of cards, followed by the cards of the pile in order.  The last pile
+has no marker.  Its cards run to the end of the block, or up to the
+first PACK_END byte.  Dropping that one marker is what lets the 13
+piles and 52 cards of klondike fit in 64 bytes.
+
+    P3 c1 h5 S7  P0  P2 d9 HT  SA cK
+    |________|   |   |______|  |___|   PACK_END ...
+      pile 0   pile 1  pile 2   last
+
+Unused bytes at the end are filled with PACK_END.  It has the pile bit
+and the reserved bit both set, so it can never be a valid marker or a
+valid card.  This keeps the packed form unique for each layout, which
+is required for use as a hash key.
+*/
+pub const PACK_END:u8 = 0xff;
+
+// pile marker bits and largest count that fits in a marker
+#[allow(clippy::unusual_byte_groupings)]
+const PILE_BIT:u8 = 0b1_0_00_0000;
+#[allow(clippy::unusual_byte_groupings)]
+const PILE_RESERVED:u8 = 0b0_1_00_0000;
+#[allow(clippy::unusual_byte_groupings)]
+const PILE_MAX:usize = 0b0_0_11_1111;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Layout {
     piles: Vec<Vec<Card>>,
+    hash: u64,                          // zobrist, see below
+    anywhere: Vec<u64>,                 // each pile's, in no place
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct LayoutPacked {
     cards: [Card;PACK_SIZE],
 }
 
 impl Default for LayoutPacked {
     fn default() -> LayoutPacked {
-        LayoutPacked { cards: [Card { code: 0 };PACK_SIZE] }
+        LayoutPacked { cards: [Card { code: PACK_END };PACK_SIZE] }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackError {
+    NoPiles,                                   // layout has no piles
+    TooBig      { needed:usize },              // bytes needed > PACK_SIZE
+    PileTooBig  { pile:usize, n:usize },       // count does not fit marker
+    BadCard     { pile:usize, code:u8 },       // not a valid card
+    BadMarker   { offset:usize, code:u8 },     // unpack found junk
+    Truncated   { pile:usize },                // marker count runs off end
+}
+
+impl std::fmt::Display for PackError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use PackError::*;
+    match self {
+        NoPiles =>
+            write!(f, "layout has no piles"),
+        TooBig { needed } =>
+            write!(f, "layout needs {} bytes, only {} available",
+                needed, PACK_SIZE),
+        PileTooBig { pile, n } =>
+            write!(f, "pile {} has {} cards, limit is {}",
+                pile, n, PILE_MAX),
+        BadCard { pile, code } =>
+            write!(f, "pile {} holds invalid card 0x{:02x}", pile, code),
+        BadMarker { offset, code } =>
+            write!(f, "byte {} is 0x{:02x}, expected a pile marker",
+                offset, code),
+        Truncated { pile } =>
+            write!(f, "pile {} runs past end of packed layout", pile),
     }
+}}
+
+impl std::error::Error for PackError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextErrorKind {
+    Empty,                                      // no piles at all
+    ExpectedPile(char),                         // found this instead of P
+    BadCount,                                   // no digits after P
+    BadSuit(char),
+    BadRank(char),
+    MissingRank,                                // text ends after suit
+    MixedCase(char, char),                      // suit and rank differ
+    CountMismatch { pile:usize, expected:usize, found:usize },
 }
 
+// col counts characters from 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextError {
+    pub col  : usize,
+    pub kind : TextErrorKind,
+}
+
+impl std::fmt::Display for TextError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use TextErrorKind::*;
+    write!(f, "layout text column {}: ", self.col)?;
+    match self.kind {
+        Empty =>
+            write!(f, "no piles"),
+        ExpectedPile(c) =>
+            write!(f, "expected pile marker 'P', found '{}'", c),
+        BadCount =>
+            write!(f, "expected number of cards after 'P'"),
+        BadSuit(c) =>
+            write!(f, "expected suit letter C H D S, found '{}'", c),
+        BadRank(c) =>
+            write!(f, "expected rank A 2-9 T J Q K, found '{}'", c),
+        MissingRank =>
+            write!(f, "card has suit but no rank"),
+        MixedCase(s, r) =>
+            write!(f, "card \"{}{}\" mixes upper and lower case", s, r),
+        CountMismatch { pile, expected, found } =>
+            write!(f, "pile {} should have {} cards, found {}",
+                pile, expected, found),
+    }
+}}
+
+impl std::error::Error for TextError {}
+
+/*----------------------------------------------------------------------
+Construct and inspect
+*/
+
+impl Layout {
+pub fn new(piles:Vec<Vec<Card>>) -> Layout {
+    let anywhere = vec![0; piles.len()];
+    let mut lo = Layout { piles, hash: 0, anywhere };
+    for ipile in 0..lo.piles.len() {
+        lo.toggle_tail(ipile, 0);
+    }
+    lo
+}}
+
+impl Layout {
+pub fn piles(&self) -> &[Vec<Card>] {
+    &self.piles
+}}
+
+impl Layout {
+pub fn pile(&self, ipile:usize) -> &[Card] {
+    &self.piles[ipile]
+}}
+
 /*----------------------------------------------------------------------
 Move n cards from the tail of one pile to the tail of another pile.
 Maintain same order.
@@ -64,12 +201,18 @@ for consistency with the rules of the game.
 
 impl Layout {
 pub fn move_tail(&mut self, from_pile:usize, to_pile:usize, n:usize) {
+    let m = self.piles[from_pile].len()-n;
+    let to_len = self.piles[to_pile].len();
+    if from_pile == to_pile {
+        return;                         // the cards end where they were
+    }
+    self.toggle_tail(from_pile, m);
     let fp = &mut self.piles[from_pile];
-    let m = fp.len()-n;
     let tail = fp[m..].to_vec();
     fp.truncate(m);
     let tp = &mut self.piles[to_pile];
     tp.extend_from_slice(&tail);
+    self.toggle_tail(to_pile, to_len);
 }}
 
 /*
@@ -87,30 +230,268 @@ fn test_move_tail() {
 
 impl Layout {
 
+/*----------------------------------------------------------------------
+Text notation
+
+A compact text form of a layout, for save files, bug reports and test
+fixtures.  Each pile is "P" and its number of cards in decimal, followed
+by its cards in order, two letters each, as in Card::to_text.  Upper
+case is face up, lower case is face down.
+
+    P0P0P2H3d7P1SA          three piles: empty, two cards, one card
+
+to_text never writes spaces, but from_text skips any white space
+between tokens, so long layouts can be broken up for reading.  The "P"
+may be either case.  The two letters of a card must be the same case.
+
+This notation is stable.  Text written now will always read back to the
+same layout.  Any future change will only add new forms.
+*/
+
 pub fn to_text(&self) -> String {
-    let out = String::new();
+    let mut out = String::new();
+    for pile in &self.piles {
+        out += &format!("P{}", pile.len());
+        for card in pile {
+            out += &card.to_text();
+        }
+    }
     out
 }
 
-pub fn pack(&self) -> LayoutPacked {
+pub fn from_text(text:&str) -> Result<Layout, TextError> {
+    let chars:Vec<char> = text.chars().collect();
+    let mut i = 0;
+    let skip_space = |i:&mut usize| {
+        while *i < chars.len() && chars[*i].is_whitespace() { *i += 1; }
+    };
+    let err = |col:usize, kind:TextErrorKind| Err(TextError { col, kind });
+    let mut piles:Vec<Vec<Card>> = vec![];
+    skip_space(&mut i);
+    if i == chars.len() {
+        return err(i, TextErrorKind::Empty);
+    }
+    while i < chars.len() {
+        // pile marker and count
+        if !matches!(chars[i], 'P'|'p') {
+            return err(i, TextErrorKind::ExpectedPile(chars[i]));
+        }
+        i += 1;
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() { i += 1; }
+        let digits:String = chars[start..i].iter().collect();
+        let n:usize = match digits.parse() {
+            Ok(n) => n,
+            Err(_) => return err(start, TextErrorKind::BadCount),
+        };
+        // cards
+        let ipile = piles.len();
+        let mut pile:Vec<Card> = vec![];
+        skip_space(&mut i);
+        while i < chars.len() && !matches!(chars[i], 'P'|'p') {
+            let (sc, rc) = (chars[i], chars.get(i+1).copied());
+            let Some(suit) = suit_from_letter(sc) else {
+                return err(i, TextErrorKind::BadSuit(sc));
+            };
+            let Some(rc) = rc else {
+                return err(i+1, TextErrorKind::MissingRank);
+            };
+            let Some(rank) = rank_from_letter(rc) else {
+                return err(i+1, TextErrorKind::BadRank(rc));
+            };
+            let face_up = sc.is_ascii_uppercase();
+            if rc.is_ascii_alphabetic() && rc.is_ascii_uppercase() != face_up {
+                return err(i, TextErrorKind::MixedCase(sc, rc));
+            }
+            pile.push(Card::pack(&CardUnpacked {
+                suit, rank, face_up, ..Default::default() }));
+            i += 2;
+            skip_space(&mut i);
+        }
+        if pile.len() != n {
+            return err(start - 1, TextErrorKind::CountMismatch {
+                pile:ipile, expected:n, found:pile.len() });
+        }
+        piles.push(pile);
+    }
+    Ok(Layout::new(piles))
+}
+
+/*----------------------------------------------------------------------
+Pack and unpack, see "Packed format" above
+*/
+
+pub fn pack(&self) -> Result<LayoutPacked, PackError> {
+    pack_piles(self.piles.iter().map(|p| &p[..]))
+}
+
+// packed form of self.permuted(real), without making it
+pub fn pack_permuted(&self, real:&[usize]) -> Result<LayoutPacked, PackError> {
+    pack_piles(real.iter().map(|r| &self.piles[*r][..]))
+}
+
+pub fn unpack(lp:LayoutPacked) -> Result<Layout, PackError> {
+    let bytes = &lp.cards;
+    // find end of used bytes
+    let end = bytes.iter()
+        .position(|c| c.code == PACK_END)
+        .unwrap_or(PACK_SIZE);
+    let mut piles:Vec<Vec<Card>> = vec![];
+    let mut i = 0;
+    loop {
+        let ipile = piles.len();
+        let c = if i < end { bytes[i].code } else { 0 };
+        let marked = (i < end) && (0 != (c & PILE_BIT));
+        if !marked {
+            // the last pile, no marker
+            let pile = bytes[i..end].to_vec();
+            if let Some(bad) = pile.iter().find(|c| !c.valid()) {
+                return Err(PackError::BadCard { pile:ipile, code:bad.code });
+            }
+            piles.push(pile);
+            break;
+        }
+        if 0 != (c & PILE_RESERVED) {
+            return Err(PackError::BadMarker { offset:i, code:c });
+        }
+        let n = (c & !PILE_BIT) as usize;
+        i += 1;
+        if i + n > end {
+            return Err(PackError::Truncated { pile:ipile });
+        }
+        let pile = bytes[i..i+n].to_vec();
+        if let Some(bad) = pile.iter().find(|c| !c.valid()) {
+            return Err(PackError::BadCard { pile:ipile, code:bad.code });
+        }
+        piles.push(pile);
+        i += n;
+    }
+    // everything after the end must be padding
+    if let Some(off) = bytes[end..].iter().position(|c| c.code != PACK_END) {
+        let offset = end + off;
+        return Err(PackError::BadMarker { offset, code:bytes[offset].code });
+    }
+    Ok(Layout::new(piles))
+}
+}  // end impl Layout
+
+fn pack_piles<'a, I>(piles:I) -> Result<LayoutPacked, PackError>
+    where I: ExactSizeIterator<Item=&'a [Card]> + Clone
+{
+    let npiles = piles.len();
+    if 0 == npiles {
+        return Err(PackError::NoPiles);
+    }
     // check for enough room
-    Default::default()
+    let ncards:usize = piles.clone().map(|p| p.len()).sum();
+    let needed = ncards + npiles - 1;
+    if needed > PACK_SIZE {
+        return Err(PackError::TooBig { needed });
+    }
+    let mut lp = LayoutPacked::default();
+    let mut i = 0;
+    for (ipile, pile) in piles.enumerate() {
+        if ipile + 1 < npiles {
+            if pile.len() > PILE_MAX {
+                return Err(PackError::PileTooBig { pile:ipile, n:pile.len() });
+            }
+            lp.cards[i] = Card { code: PILE_BIT | (pile.len() as u8) };
+            i += 1;
+        }
+        for card in pile {
+            if !card.valid() {
+                return Err(PackError::BadCard { pile:ipile, code:card.code });
+            }
+            lp.cards[i] = *card;
+            i += 1;
+        }
+    }
+    Ok(lp)
 }
 
-pub fn unpack(lp:LayoutPacked) -> Layout {
-    Default::default()
+// raw bytes, for save files
+impl LayoutPacked {
+pub fn to_bytes(&self) -> [u8;PACK_SIZE] {
+    self.cards.map(|c| c.code)
+}}
+
+// no check here, unpack finds any junk
+impl LayoutPacked {
+pub fn from_bytes(bytes:[u8;PACK_SIZE]) -> LayoutPacked {
+    LayoutPacked { cards: bytes.map(|code| Card { code }) }
+}}
+
+#[test]
+fn test_pack_and_unpack() {
+    use crate::deck::Deck;
+
+    // split a deck into piles of the given sizes
+    fn split(sizes:&[usize]) -> Layout {
+        let mut cards = vec_card_from_vec_u8(Deck::new().cards);
+        // some face up, some face down
+        for (i, c) in cards.iter_mut().enumerate() {
+            c.set_face_up(0 == i%3);
+        }
+        let mut piles:Vec<Vec<Card>> = vec![];
+        let mut i = 0;
+        for n in sizes {
+            piles.push(cards[i..i+n].to_vec());
+            i += n;
+        }
+        Layout::new(piles)
+    }
+    fn t(sizes:&[usize]) {
+        let lo = split(sizes);
+        let lp = lo.pack().unwrap();
+        assert_eq!(Layout::unpack(lp).unwrap(), lo);
+    }
+    // klondike deal: 13 piles, 52 cards, exactly fills the block
+    t(&[24,0,0,0,0,0,1,2,3,4,5,6,7]);
+    t(&[0,0,0,0,0,0,0,0,0,0,0,0,52]);
+    t(&[52,0,0,0,0,0,0,0,0,0,0,0,0]);
+    t(&[0]);
+    t(&[5]);
+    t(&[0,0]);
+    t(&[3,0,4,0]);
+    t(&[13,13,13,13]);
+
+    // too big
+    let lo = split(&[24,1,0,0,0,0,1,2,3,4,5,6,6,0]);
+    assert_eq!(lo.pack(), Err(PackError::TooBig { needed:65 }));
+    assert_eq!(Layout::new(vec![]).pack(), Err(PackError::NoPiles));
+
+    // equal layouts give equal keys, different layouts differ
+    use std::collections::HashSet;
+    let mut hs:HashSet<LayoutPacked> = HashSet::new();
+    hs.insert(split(&[3,4,5]).pack().unwrap());
+    assert!(hs.contains(&split(&[3,4,5]).pack().unwrap()));
+    assert!(!hs.contains(&split(&[3,5,4]).pack().unwrap()));
+    assert!(!hs.contains(&split(&[3,4,5,0]).pack().unwrap()));
+
+    // corrupt packed data is rejected
+    let mut lp = split(&[3,4]).pack().unwrap();
+    lp.cards[0] = Card { code: PILE_BIT | 9 };
+    assert_eq!(Layout::unpack(lp), Err(PackError::Truncated { pile:0 }));
+    lp.cards[0] = Card { code: PILE_BIT | PILE_RESERVED | 3 };
+    assert!(matches!(Layout::unpack(lp), Err(PackError::BadMarker {..})));
+    let mut lp = split(&[3,4]).pack().unwrap();
+    lp.cards[20] = Card { code: 0 };
+    assert!(matches!(Layout::unpack(lp), Err(PackError::BadMarker {..})));
 }
 
+impl Layout {
+
 /*----------------------------------------------------------------------
 Set tail of pile face up or face down
 */
 
 pub fn set_tail_face_up(&mut self, ipile:usize, n:usize, up:bool) {
-    let pile = &mut self.piles[ipile];
-    let len = pile.len();
-    for i in (len-n)..len {
-        pile[i].set_face_up(up);
+    let len = self.piles[ipile].len();
+    self.toggle_tail(ipile, len-n);
+    for card in &mut self.piles[ipile][(len-n)..] {
+        card.set_face_up(up);
     }
+    self.toggle_tail(ipile, len-n);
 }
 
 /*----------------------------------------------------------------------
@@ -118,8 +499,351 @@ Reverse order and reverse "face up" sense of all cards in pile
 This corresponds to turning over a stack of cards.
 */
 pub fn flip_pile(&mut self, ipile:usize) {
+    let n = self.piles[ipile].len();
+    self.flip_tail(ipile, n);
+}
+
+/*----------------------------------------------------------------------
+Same as flip_pile, but only for the last n cards of the pile.
+Dealing cards one at a time from the stock onto the waste does this.
+*/
+pub fn flip_tail(&mut self, ipile:usize, n:usize) {
+    let len = self.piles[ipile].len();
+    self.toggle_tail(ipile, len-n);
+    let tail = &mut self.piles[ipile][(len-n)..];
+    tail.reverse();
+    for card in tail {
+        card.set_face_up(!card.face_up());
+    }
+    self.toggle_tail(ipile, len-n);
 }
 
 
 }  // end impl Layout
+
+#[test]
+fn test_text() {
+    use crate::deck::Deck;
+    use crate::klondike::Rules;
+
+    // fixed text for a fixed layout, this must never change
+    let lo = Rules::default().deal(&Deck::new());
+    let text = lo.to_text();
+    assert_eq!(text, concat!(
+        "P24c3c4c5c6c7c8c9ctcjcqckhah2h3h4h5h6h7h8h9hthjhqhk",
+        "P0P0P0P0P0",
+        "P1SA",
+        "P2s2S8",
+        "P3s3s9DA",
+        "P4s4std2D6",
+        "P5s5sjd3d7DT",
+        "P6s6sqd4d8djDK",
+        "P7s7skd5d9dqcaC2",
+    ));
+
+    // round trip, and agrees with pack
+    fn t(lo:&Layout) {
+        let lo2 = Layout::from_text(&lo.to_text()).unwrap();
+        assert_eq!(&lo2, lo);
+        assert_eq!(lo2.pack(), lo.pack());
+    }
+    t(&lo);
+    for deal in 0..20 {
+        t(&Rules::default().deal(&Deck::from_deal(deal)));
+    }
+    t(&Layout::new(vec![vec![]]));
+
+    // white space and lower case pile marker are fine
+    let spaced = Layout::from_text(" p0 P2 s2 S8\n P1 SA ").unwrap();
+    assert_eq!(spaced.to_text(), "P0P2s2S8P1SA");
+
+    // errors
+    use TextErrorKind::*;
+    fn e(text:&str, col:usize, kind:TextErrorKind) {
+        assert_eq!(Layout::from_text(text), Err(TextError { col, kind }));
+    }
+    e("",           0, Empty);
+    e("  ",         2, Empty);
+    e("SA",         0, ExpectedPile('S'));
+    e("P",          1, BadCount);
+    e("PSA",        1, BadCount);
+    e("P1XA",       2, BadSuit('X'));
+    e("P1S1",       3, BadRank('1'));
+    e("P1S",        3, MissingRank);
+    e("P1Sa",       2, MixedCase('S', 'a'));
+    e("P2SAP0",     0, CountMismatch { pile:0, expected:2, found:1 });
+    e("P0P1",       2, CountMismatch { pile:1, expected:1, found:0 });
+    let msg = Layout::from_text("P1Sx").unwrap_err().to_string();
+    assert_eq!(msg, "layout text column 3: expected rank A 2-9 T J Q K, found 'x'");
+}
+
+#[test]
+fn test_flip_pile() {
+    fn c(code:u8) -> Card { Card { code } }
+    let mut lo = Layout::new(vec![
+        vec![c(0x01), c(0x42), c(0x03)],
+        vec![c(0x44), c(0x45), c(0x46), c(0x07)],
+    ]);
+    lo.flip_pile(0);
+    assert_eq!(lo.pile(0), &[c(0x43), c(0x02), c(0x41)]);
+    lo.flip_tail(1, 2);
+    assert_eq!(lo.pile(1), &[c(0x44), c(0x45), c(0x47), c(0x06)]);
+    // flipping twice restores the pile
+    lo.flip_pile(1);
+    lo.flip_pile(1);
+    assert_eq!(lo.pile(1), &[c(0x44), c(0x45), c(0x47), c(0x06)]);
+    lo.flip_tail(1, 0);
+    assert_eq!(lo.pile(1), &[c(0x44), c(0x45), c(0x47), c(0x06)]);
+}
+
+/*----------------------------------------------------------------------
+Zobrist hash
+
+A 64 bit hash of the layout, kept up to date as cards move, so a
+position can be looked up without packing it.  Each card, in each
+pile, at each depth in the pile, face up or down, has a random key.
+The hash is the exclusive or of the keys of all cards as they lie.
+Moving or turning cards takes out the keys of the cards before and
+puts in their keys after, which is only as much work as the cards
+touched.
+
+Each pile also keeps the same hash of its own cards, but with the keys
+for no pile in particular, so it is the same wherever the pile is.
+This is for the hash of layouts alike but for swapped piles, see
+zobrist_alike below.
+
+The keys are not kept in a table, but made when needed by mixing the
+four numbers with the SplitMix64 finalizer.  So they are the same on
+every run and every platform, and may be used in files.
+
+Different layouts may have the same hash, though with 64 bits it is
+most unlikely.  Anything that must be exact should compare the packed
+layouts too.
+*/
+
+// card identity, suit and rank, without the face up bit
+#[allow(clippy::unusual_byte_groupings)]
+const CARD_IDENT:u8 = 0b0_0_11_1111;
+
+// key numbers beyond any card key, for counts kept beside a layout
+const EXTRA_KEY:u64 = 1 << 32;
+
+// pile number for the keys of no pile in particular
+const ANYWHERE:usize = 0xff;
+
+// key numbers beyond any card or count key, for places of piles
+const PLACE_KEY:u64 = 2 << 32;
+
+fn zobrist_key(card:Card, ipile:usize, depth:usize) -> u64 {
+    let ident = (card.code & CARD_IDENT) as u64;
+    let idx = ident << 16 | (ipile as u64) << 8 | (depth as u64) << 1
+        | card.face_up() as u64;
+    SplitMix64::new(idx).next_u64()
+}
+
+impl Layout {
+pub fn zobrist(&self) -> u64 {
+    self.hash
+}}
+
+// the hash worked out from scratch, which must equal zobrist()
+impl Layout {
+pub fn zobrist_full(&self) -> u64 {
+    (0..self.piles.len()).fold(0, |h, ipile| h ^ self.zobrist_tail(ipile, 0, ipile))
+}}
+
+// keys of the cards in a pile from depth start to the top, as if in
+// pile slot
+impl Layout {
+fn zobrist_tail(&self, ipile:usize, start:usize, slot:usize) -> u64 {
+    self.piles[ipile][start..].iter().enumerate()
+        .fold(0, |h, (i, card)| h ^ zobrist_key(*card, slot, start + i))
+}}
+
+// take the keys of the cards from depth start to the top out of both
+// hashes, or put them in
+impl Layout {
+fn toggle_tail(&mut self, ipile:usize, start:usize) {
+    self.hash ^= self.zobrist_tail(ipile, start, ipile);
+    self.anywhere[ipile] ^= self.zobrist_tail(ipile, start, ANYWHERE);
+}}
+
+// key for a count kept beside the layout, such as stock recycles,
+// to exclusive or with the layout hash.  A count of zero adds nothing.
+pub fn zobrist_count(n:usize) -> u64 {
+    match n {
+        0 => 0,
+        n => SplitMix64::new(EXTRA_KEY | n as u64).next_u64(),
+    }
+}
+
+#[test]
+fn test_zobrist() {
+    use crate::deck::Deck;
+    use crate::klondike::Rules;
+    let rules = Rules::default();
+    let mut lo = rules.deal(&Deck::new());
+    assert_eq!(lo.zobrist(), lo.zobrist_full());
+    assert_ne!(lo.zobrist(), 0);
+    let start = lo.clone();
+
+    // follow every kind of change with the hash from scratch
+    let mut rng = SplitMix64::new(99);
+    for _ in 0..2000 {
+        let n_piles = lo.piles().len();
+        let a = rng.below(n_piles as u64) as usize;
+        let b = rng.below(n_piles as u64) as usize;
+        let len = lo.pile(a).len();
+        let n = rng.below(len as u64 + 1) as usize;
+        match rng.below(4) {
+            0 => lo.move_tail(a, b, n),
+            1 => lo.set_tail_face_up(a, n, 0 == rng.below(2)),
+            2 => lo.flip_pile(a),
+            _ => lo.flip_tail(a, n),
+        }
+        assert_eq!(lo.zobrist(), lo.zobrist_full());
+        let groups = rules.symmetric_piles();
+        let fresh = Layout::new(lo.piles().to_vec());
+        assert_eq!(lo.zobrist_alike(&groups), fresh.zobrist_alike(&groups));
+    }
+
+    // same layout, same hash, however it was reached
+    let back = Layout::from_text(&lo.to_text()).unwrap();
+    assert_eq!(back.zobrist(), lo.zobrist());
+    let back = Layout::unpack(lo.pack().unwrap()).unwrap();
+    assert_eq!(back.zobrist(), lo.zobrist());
+    assert_eq!(back, lo);
+
+    // turning a card over, or moving it, changes the hash
+    let mut up = start.clone();
+    up.set_tail_face_up(0, 1, true);
+    assert_ne!(up.zobrist(), start.zobrist());
+    up.set_tail_face_up(0, 1, false);
+    assert_eq!(up.zobrist(), start.zobrist());
+    let mut moved = start.clone();
+    moved.move_tail(0, 1, 1);
+    assert_ne!(moved.zobrist(), start.zobrist());
+    moved.move_tail(1, 0, 1);
+    assert_eq!(moved, start);
+    // onto the same pile, nothing moves
+    let len = moved.pile(6).len();
+    moved.move_tail(6, 6, len);
+    assert_eq!(moved, start);
+
+    assert_eq!(Layout::default().zobrist(), Layout::new(vec![]).zobrist());
+    assert_eq!(zobrist_count(0), 0);
+    assert_ne!(zobrist_count(1), zobrist_count(2));
+}
+
+
+/*----------------------------------------------------------------------
+Canonical order of piles
+
+Some games have piles that play alike, so a layout with two of them
+swapped is the same position in all that matters.  Given groups of
+such piles, canonical_order sorts the piles in each group by their
+cards, and gives for each place in the sorted layout the real pile
+that goes there.  Layouts that differ only by swaps within the groups
+have the same sorted layout.  Piles not in any group stay where they
+are.  Equal piles, which can only be empty ones, keep their order.
+
+zobrist_alike gives a hash of the symmetry class, the same for all
+layouts with the same sorted layout, and but for chance different for
+any other.  It is not the zobrist hash of the sorted layout.  It comes
+from the hash each pile keeps as cards move, so it is work for each
+pile, not each card, and needs no sorting.  The packed sorted layout,
+for exact comparison, comes from pack_permuted.
+
+The game knows which piles play alike, see klondike.rs.
+*/
+
+impl Layout {
+pub fn canonical_order(&self, groups:&[std::ops::Range<usize>]) -> Vec<usize> {
+    let mut real:Vec<usize> = (0..self.piles.len()).collect();
+    for group in groups {
+        real[group.clone()].sort_by(|a, b| {
+            let a = self.piles[*a].iter().map(|c| c.code);
+            let b = self.piles[*b].iter().map(|c| c.code);
+            a.cmp(b)
+        });
+    }
+    real
+}}
+
+// the layout with pile i taken from real pile real[i]
+impl Layout {
+pub fn permuted(&self, real:&[usize]) -> Layout {
+    Layout::new(real.iter().map(|r| self.piles[*r].clone()).collect())
+}}
+
+// hash of the symmetry class of the layout, see above.  Each pile is
+// keyed by its place, or for piles in a group by the group, and the
+// sum does not care about the order within a group.
+impl Layout {
+pub fn zobrist_alike(&self, groups:&[std::ops::Range<usize>]) -> u64 {
+    self.anywhere.iter().enumerate().fold(0, |h:u64, (ipile, pile_hash)| {
+        let place = groups.iter().find(|g| g.contains(&ipile)).map_or(ipile, |g| g.start);
+        let place_key = SplitMix64::new(PLACE_KEY | place as u64).next_u64();
+        h.wrapping_add(SplitMix64::new(pile_hash ^ place_key).next_u64())
+    })
+}}
+
+#[test]
+fn test_canonical_order() {
+    fn c(code:u8) -> Card { Card { code } }
+    let lo = Layout::new(vec![
+        vec![c(0x05)],
+        vec![c(0x13), c(0x52)],
+        vec![],
+        vec![c(0x01), c(0x44)],
+        vec![c(0x20)],
+    ]);
+    // piles 1 and 2 play alike, and so do 3 and 4
+    let groups = [1..3, 3..5];
+    let real = lo.canonical_order(&groups);
+    assert_eq!(real, vec![0, 2, 1, 3, 4]);
+    let canon = lo.permuted(&real);
+    assert_eq!(canon.pile(1), &[]);
+    assert_eq!(canon.pile(2), &[c(0x13), c(0x52)]);
+    assert_eq!(canon.zobrist_alike(&groups), lo.zobrist_alike(&groups));
+    assert_eq!(canon.pack(), lo.pack_permuted(&real));
+
+    // any swap within the group gives the same canonical layout
+    let swapped = lo.permuted(&[0, 2, 1, 4, 3]);
+    let again = swapped.permuted(&swapped.canonical_order(&groups));
+    assert_eq!(again, canon);
+    assert_eq!(swapped.zobrist_alike(&groups), lo.zobrist_alike(&groups));
+    // but not a swap between groups
+    let swapped = lo.permuted(&[0, 3, 2, 1, 4]);
+    assert_ne!(swapped.permuted(&swapped.canonical_order(&groups)), canon);
+
+    // no groups, nothing moves
+    assert_eq!(lo.canonical_order(&[]), vec![0, 1, 2, 3, 4]);
+    let swapped = lo.permuted(&[0, 2, 1, 3, 4]);
+    assert_ne!(swapped.zobrist_alike(&[]), lo.zobrist_alike(&[]));
+
+    // any order within the groups, the same class
+    for real in [[0, 2, 1, 3, 4], [0, 1, 2, 4, 3], [0, 2, 1, 4, 3]] {
+        assert_eq!(lo.permuted(&real).zobrist_alike(&groups), lo.zobrist_alike(&groups));
+    }
+    // a pile moved between groups, or a card turned, another class
+    let other = lo.permuted(&[0, 3, 2, 1, 4]);
+    assert_ne!(other.zobrist_alike(&groups), lo.zobrist_alike(&groups));
+    let mut other = lo.clone();
+    other.set_tail_face_up(3, 1, false);
+    assert_ne!(other.zobrist_alike(&groups), lo.zobrist_alike(&groups));
+
+    // cards swapped between alike piles at the same depth are not alike
+    let mut a = lo.clone();
+    a.move_tail(1, 2, 1);
+    a.move_tail(3, 1, 1);
+    a.move_tail(2, 3, 1);
+    assert_eq!(a.pile(1), &[c(0x13), c(0x44)]);
+    assert_eq!(a.pile(3), &[c(0x01), c(0x52)]);
+    assert_eq!(a.zobrist(), a.zobrist_full());
+    let group = 1..5;
+    let all = std::slice::from_ref(&group);
+    assert_ne!(a.zobrist_alike(all), lo.zobrist_alike(all));
+}
+
 // end mod layout ------------------------------------------------------
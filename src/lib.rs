@@ -11,7 +11,18 @@ Licenses with other terms may be negotiated by contacting the author.
 This statement applies to all the files listed below.
 */
 
+pub mod autoplay;
 pub mod card;
+pub mod certificate;
+pub mod dealgen;
 pub mod deck;
+pub mod difficulty;
+pub mod hindsight;
+pub mod hint;
+pub mod history;
+pub mod klondike;
+pub mod kpat;
 pub mod layout;
 pub mod misc;
+pub mod save;
+pub mod solver;
@@ -115,4 +115,180 @@ pub fn rand_vec_u8(n:usize, r:u8) -> Vec<u8> {
     vu8
 }
 
+/*----------------------------------------------------------------------
+Seedable random number generator
+
+The rand crate does not promise that its seeded generators give the
+same numbers across versions or platforms.  We need that promise for
+deal numbers, so use our own.  This is SplitMix64 from Sebastiano Vigna,
+which is small, fast, and has published reference outputs.
+
+https://prng.di.unimi.it/splitmix64.c
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+pub fn new(seed:u64) -> SplitMix64 {
+    SplitMix64 { state: seed }
+}}
+
+impl SplitMix64 {
+pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}}
+
+// uniform in 0..n, without modulo bias
+impl SplitMix64 {
+pub fn below(&mut self, n:u64) -> u64 {
+    assert!(n > 0);
+    let zone = u64::MAX - (u64::MAX % n);
+    loop {
+        let v = self.next_u64();
+        if v < zone {
+            return v % n;
+        }
+    }
+}}
+
+#[test]
+fn test_splitmix64() {
+    // reference values from splitmix64.c with seed 1234567
+    let mut rng = SplitMix64::new(1234567);
+    assert_eq!(rng.next_u64(), 6457827717110365317);
+    assert_eq!(rng.next_u64(), 3203168211198807973);
+    assert_eq!(rng.next_u64(), 9817491932198370423);
+    assert_eq!(rng.next_u64(), 4593380528125082431);
+    assert_eq!(rng.next_u64(), 16408922859458223821);
+    // below stays in range and hits every value
+    let mut seen = [false;10];
+    for _ in 0..1000 {
+        let v = rng.below(10) as usize;
+        seen[v] = true;
+    }
+    assert!(seen.iter().all(|s| *s));
+}
+
+/*----------------------------------------------------------------------
+FNV-1a hash, 64 bit
+
+For hashes written to files, which must not change across versions or
+platforms, as the std hasher may.
+
+http://www.isthe.com/chongo/tech/comp/fnv/
+*/
+
+pub const FNV_OFFSET:u64 = 0xcbf29ce484222325;
+pub const FNV_PRIME:u64 = 0x100000001b3;
+
+// continue a hash over more bytes, start with FNV_OFFSET
+pub fn fnv1a(hash:u64, bytes:&[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+#[test]
+fn test_fnv1a() {
+    // reference values from the FNV test suite
+    assert_eq!(fnv1a(FNV_OFFSET, b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a(FNV_OFFSET, b"foobar"), 0x85944171f73967e8);
+    assert_eq!(fnv1a(fnv1a(FNV_OFFSET, b"foo"), b"bar"), 0x85944171f73967e8);
+}
+
+/*----------------------------------------------------------------------
+Pass through hasher
+
+For hash maps keyed by a u64 that is already a good hash, such as a
+zobrist hash of a layout.  Hashing it again would only cost time.
+*/
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PassThrough {
+    hash: u64,
+}
+
+impl std::hash::Hasher for PassThrough {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+    fn write(&mut self, bytes:&[u8]) {
+        // only for keys other than u64, which should not happen
+        self.hash = fnv1a(self.hash ^ FNV_OFFSET, bytes);
+    }
+    fn write_u64(&mut self, n:u64) {
+        self.hash = n;
+    }
+}
+
+pub type PassThroughMap<K, V> =
+    std::collections::HashMap<K, V, std::hash::BuildHasherDefault<PassThrough>>;
+
+#[test]
+fn test_pass_through() {
+    use std::hash::BuildHasher;
+    let b = std::hash::BuildHasherDefault::<PassThrough>::default();
+    assert_eq!(b.hash_one(0x1234_5678_9abc_def0_u64), 0x1234_5678_9abc_def0);
+    let mut m:PassThroughMap<u64, usize> = PassThroughMap::default();
+    m.insert(7, 1);
+    m.insert(u64::MAX, 2);
+    assert_eq!(m.get(&7), Some(&1));
+    assert_eq!(m.get(&u64::MAX), Some(&2));
+    assert_eq!(m.get(&8), None);
+}
+
+/*----------------------------------------------------------------------
+Command line arguments
+
+Numbers given to the programs in src/bin by position.  A missing
+argument takes the default, but one that is not a number is an error,
+not the default, so a slip of the finger is not run as something else.
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArgError {
+    pub index : usize,                  // in args, the program is 0
+    pub text  : String,
+}
+
+impl std::fmt::Display for ArgError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "argument {} is not a number: {}", self.index, self.text)
+}}
+
+impl std::error::Error for ArgError {}
+
+pub fn arg_u64(args:&[String], index:usize, default:u64) -> Result<u64, ArgError> {
+    match args.get(index) {
+        Some(text) => text.parse()
+            .map_err(|_| ArgError { index, text: text.clone() }),
+        None => Ok(default),
+    }
+}
+
+// the same, but print the error and exit, for main
+pub fn arg_or_exit(args:&[String], index:usize, default:u64) -> u64 {
+    arg_u64(args, index, default).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+#[test]
+fn test_arg_u64() {
+    let args:Vec<String> = ["prog", "12", "x7", ""].iter().map(|s| s.to_string()).collect();
+    assert_eq!(arg_u64(&args, 1, 5), Ok(12));
+    assert_eq!(arg_u64(&args, 4, 5), Ok(5));
+    let e = arg_u64(&args, 2, 5).unwrap_err();
+    assert_eq!(e, ArgError { index: 2, text: "x7".to_string() });
+    assert_eq!(e.to_string(), "argument 2 is not a number: x7");
+    assert!(arg_u64(&args, 3, 5).is_err());
+}
+
 // End misc module -----------------------------------------------------
@@ -0,0 +1,747 @@
+/*----------------------------------------------------------------------
+Save module
+
+Save a whole game to a file and load it back.  This is the plan from
+log.txt: name of game, names of piles, deck after shuffling, a vector
+of positions with ids and parent ids, the current position and the
+marked positions.  Also the rules, since the same deck plays
+differently with draw one or draw three.
+
+Every position visited is kept, with all the branches out of it and
+which one redo follows.  The path to the current position is kept
+whole, not just its id, so undo goes back the way the player came.
+
+There are two forms with the same content.
+
+The text form is one line per item, a keyword and then values.  The
+positions are in the compact layout notation, so a save file can be
+read, and edited, by hand.
+
+    solitaire-save 1
+    game klondike
+    rules 3 unlimited
+    auto on
+    piles S W F1 F2 F3 F4 T1 T2 T3 T4 T5 T6 T7
+    deck P52c3c4c5 ...
+    pos 0 - - 0 P24c3c4 ...
+    pos 1 0 D3 0 P21c3c4 ...
+    branch 0 1 D3
+    redo 0 0
+    path 0,1
+    mark 0,1 before the draw
+    end
+
+    pos    id  parent  move from parent  recycles  layout
+    branch from  to  move
+    redo   id  branch number
+    mark   path  name, the rest of the line
+
+Parent and move are "-" for the start position.  Rules give the draw
+count and then the number of passes, or "unlimited".  The auto line,
+"on" or "off", is for auto move, see History::auto_move.  It came
+after version 1 was out, so it is a line of its own that older
+readers skip.  When it is missing auto move is on.  The same goes for
+the AUTO section below.
+
+The binary form is for big histories.  Positions are the 64 bytes of
+LayoutPacked.  Numbers are little endian.
+
+    magic "SOLS", version u16
+    sections of  tag [u8;4], length u32, contents
+
+    GAME  name
+    RULE  draw u32, passes u32 (0 is unlimited)
+    AUTO  auto move u32 (0 is off), on when missing
+    PILE  names separated by spaces
+    DECK  52 card codes
+    NODE  count u32, then for each position
+            packed [u8;64], parent u32, move from to n,
+            recycles u32, redo u32, branch count u32,
+            then for each branch  move from to n, to u32
+    PATH  count u32, ids u32
+    MARK  count u32, then for each mark
+            name length u32, name, path count u32, ids u32
+    END.  empty
+
+A missing parent or redo is u32::MAX.
+
+Both forms start with a version number.  A reader refuses any version
+newer than its own.  Within one version, new kinds of lines or sections
+may be added, and older readers skip what they do not know.  A change
+that older readers could not skip gets a new version number.
+
+Both forms end with an end marker, so a file cut short is found out,
+not taken for a shorter game.  Everything read is checked to fit
+together before a History is built from it.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use std::collections::HashSet;
+
+use crate::card::*;
+use crate::deck::*;
+use crate::history::*;
+use crate::klondike::*;
+use crate::layout::*;
+
+pub const SAVE_VERSION:u32 = 1;
+pub const SAVE_TEXT_HEADER:&str = "solitaire-save";
+pub const SAVE_MAGIC:&[u8;4] = b"SOLS";
+
+const NONE_U32:u32 = u32::MAX;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveGame {
+    pub game    : String,
+    pub piles   : Vec<String>,
+    pub rules   : Rules,
+    pub deck    : Deck,
+    pub history : History,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaveError {
+    Io(String),                                 // could not read or write
+    NotSave,                                    // header missing
+    Version(u32),                               // newer than we read
+    Truncated,                                  // ends before end marker
+    Syntax      { line:usize, text:String },    // text line not understood
+    Layout      { line:usize, err:TextError },  // bad position text
+    Packed      { pos:PosId, err:PackError },   // bad position bytes
+    UnknownGame(String),
+    Invalid(String),                            // parts do not fit together
+}
+
+impl std::fmt::Display for SaveError {
+fn fmt(&self, f:&mut std::fmt::Formatter) -> std::fmt::Result {
+    use SaveError::*;
+    match self {
+        Io(s)       => write!(f, "save file: {}", s),
+        NotSave     => write!(f, "not a solitaire save file"),
+        Version(v)  => write!(f, "save file version {} is newer than {}",
+                           v, SAVE_VERSION),
+        Truncated   => write!(f, "save file is cut short"),
+        Syntax { line, text } =>
+            write!(f, "save file line {}: cannot read \"{}\"", line, text),
+        Layout { line, err } =>
+            write!(f, "save file line {}: {}", line, err),
+        Packed { pos, err } =>
+            write!(f, "save file position {}: {}", pos, err),
+        UnknownGame(s) => write!(f, "save file game unknown: \"{}\"", s),
+        Invalid(s)  => write!(f, "save file is inconsistent: {}", s),
+    }
+}}
+
+impl std::error::Error for SaveError {}
+
+impl SaveGame {
+pub fn new(rules:&Rules, deck:&Deck, history:History) -> SaveGame {
+    SaveGame {
+        game    : "klondike".to_string(),
+        piles   : (0..N_PILES).map(pile_name).collect(),
+        rules   : *rules,
+        deck    : deck.clone(),
+        history,
+    }
+}}
+
+// everything read from a file, before it is checked
+struct Parts {
+    game  : String,
+    piles : Vec<String>,
+    rules : Rules,
+    auto  : bool,                       // auto move, kept apart from rules
+    deck  : Deck,
+    nodes : Vec<Node>,
+    path  : Vec<PosId>,
+    marks : Vec<Mark>,
+}
+
+/*----------------------------------------------------------------------
+Check parts fit together and build the game
+*/
+
+fn check_path(nodes:&[Node], path:&[PosId], what:&str) -> Result<(), SaveError> {
+    if path.first() != Some(&0) {
+        return Err(SaveError::Invalid(format!("{} does not start at position 0", what)));
+    }
+    for w in path.windows(2) {
+        if !nodes[w[0]].branches.iter().any(|b| b.to == w[1]) {
+            return Err(SaveError::Invalid(format!(
+                "{} steps from {} to {} without a move", what, w[0], w[1])));
+        }
+    }
+    Ok(())
+}
+
+fn assemble(p:Parts) -> Result<SaveGame, SaveError> {
+    let bad = |s:String| Err(SaveError::Invalid(s));
+    if p.game != "klondike" {
+        return Err(SaveError::UnknownGame(p.game));
+    }
+    if p.piles.len() != N_PILES {
+        return bad(format!("{} piles named, klondike has {}", p.piles.len(), N_PILES));
+    }
+    if 0 == p.rules.draw {
+        return bad("draw count is zero".to_string());
+    }
+    if !p.deck.valid() {
+        return bad("deck is not one of each card".to_string());
+    }
+    if p.nodes.is_empty() {
+        return bad("no positions".to_string());
+    }
+    let mut seen = HashSet::new();
+    for (id, node) in p.nodes.iter().enumerate() {
+        let lo = Layout::unpack(node.packed)
+            .map_err(|err| SaveError::Packed { pos:id, err })?;
+        if lo.piles().len() != N_PILES {
+            return bad(format!("position {} has {} piles", id, lo.piles().len()));
+        }
+        match (node.parent, node.mv) {
+            (None, None) if 0 == id => {},
+            (Some(parent), Some(_)) if parent < id => {},
+            _ => return bad(format!("position {} has a bad parent", id)),
+        }
+        if !seen.insert((node.packed, node.recycles)) {
+            return bad(format!("position {} appears twice", id));
+        }
+        if node.branches.iter().any(|b| b.to >= p.nodes.len()) {
+            return bad(format!("position {} branches to nowhere", id));
+        }
+        if node.redo.is_some_and(|ib| ib >= node.branches.len()) {
+            return bad(format!("position {} redo has no branch", id));
+        }
+    }
+    check_path(&p.nodes, &p.path, "path")?;
+    for m in &p.marks {
+        check_path(&p.nodes, &m.path, &format!("mark \"{}\"", m.name))?;
+    }
+    Ok(SaveGame {
+        game    : p.game,
+        piles   : p.piles,
+        rules   : Rules { auto_move: p.auto, ..p.rules },
+        deck    : p.deck,
+        history : History::from_parts(p.nodes, p.path, p.marks),
+    })
+}
+
+/*----------------------------------------------------------------------
+Text form
+*/
+
+fn deck_text(deck:&Deck) -> String {
+    Layout::new(vec![vec_card_from_vec_u8(deck.cards.clone())]).to_text()
+}
+
+fn opt_text(x:Option<usize>) -> String {
+    match x {
+        Some(x) => x.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn ids_text(ids:&[PosId]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+}
+
+impl SaveGame {
+pub fn to_text(&self) -> String {
+    let h = &self.history;
+    let mut out = String::new();
+    out += &format!("{} {}\n", SAVE_TEXT_HEADER, SAVE_VERSION);
+    out += &format!("game {}\n", self.game);
+    out += &format!("rules {}\n", self.rules.to_text());
+    out += &format!("auto {}\n", if self.rules.auto_move { "on" } else { "off" });
+    out += &format!("piles {}\n", self.piles.join(" "));
+    out += &format!("deck {}\n", deck_text(&self.deck));
+    for (id, node) in h.nodes().iter().enumerate() {
+        let mv = node.mv.map(|mv| mv.to_text()).unwrap_or("-".to_string());
+        out += &format!("pos {} {} {} {} {}\n", id, opt_text(node.parent), mv,
+            node.recycles, h.layout_at(id).to_text());
+    }
+    for (id, node) in h.nodes().iter().enumerate() {
+        for b in &node.branches {
+            out += &format!("branch {} {} {}\n", id, b.to, b.mv.to_text());
+        }
+        if let Some(ib) = node.redo {
+            out += &format!("redo {} {}\n", id, ib);
+        }
+    }
+    out += &format!("path {}\n", ids_text(h.path()));
+    for m in h.marks() {
+        out += &format!("mark {} {}\n", ids_text(&m.path), m.name);
+    }
+    out += "end\n";
+    out
+}}
+
+impl SaveGame {
+pub fn from_text(text:&str) -> Result<SaveGame, SaveError> {
+    let mut lines = text.lines().enumerate().map(|(i, s)| (i + 1, s.trim()));
+    // header
+    let Some((_, first)) = lines.next() else {
+        return Err(SaveError::NotSave);
+    };
+    let version = match first.split_whitespace().collect::<Vec<_>>()[..] {
+        [SAVE_TEXT_HEADER, v] => v.parse::<u32>().map_err(|_| SaveError::NotSave)?,
+        _ => return Err(SaveError::NotSave),
+    };
+    if version > SAVE_VERSION {
+        return Err(SaveError::Version(version));
+    }
+    // a cut may fall inside a line, look for the end before reading any
+    if !text.lines().any(|s| s.trim() == "end") {
+        return Err(SaveError::Truncated);
+    }
+    let mut p = Parts {
+        game  : String::new(),
+        piles : vec![],
+        rules : Rules::default(),
+        auto  : true,
+        deck  : Deck { cards: vec![] },
+        nodes : vec![],
+        path  : vec![],
+        marks : vec![],
+    };
+    for (line, text) in lines {
+        let syntax = || SaveError::Syntax { line, text: text.to_string() };
+        let (key, rest) = text.split_once(' ').unwrap_or((text, ""));
+        let rest = rest.trim();
+        let words:Vec<&str> = rest.split_whitespace().collect();
+        let id = |s:&str| s.parse::<usize>().ok();
+        let opt = |s:&str| if s == "-" { Some(None) } else { id(s).map(Some) };
+        let ids = |s:&str| s.split(',').map(id).collect::<Option<Vec<PosId>>>();
+        let mv = |s:&str| Move::from_text(s).ok();
+        let layout = |s:&str| Layout::from_text(s)
+            .map_err(|err| SaveError::Layout { line, err });
+        match key {
+            "" => {},
+            "game" => p.game = rest.to_string(),
+            "rules" => p.rules = Rules::from_text(rest).ok_or_else(syntax)?,
+            "auto" => p.auto = match rest {
+                "on" => true,
+                "off" => false,
+                _ => return Err(syntax()),
+            },
+            "piles" => p.piles = words.iter().map(|s| s.to_string()).collect(),
+            "deck" => {
+                let lo = layout(rest)?;
+                let [cards] = lo.piles() else { return Err(syntax()) };
+                p.deck = Deck { cards: cards.iter()
+                    .map(|c| { let mut c = *c; c.set_face_up(false); c.code })
+                    .collect() };
+            },
+            "pos" => {
+                let (Some(&pos), Some(&parent), Some(&m), Some(&recycles)) =
+                    (words.first(), words.get(1), words.get(2), words.get(3))
+                    else { return Err(syntax()) };
+                if id(pos) != Some(p.nodes.len()) {
+                    return Err(syntax());
+                }
+                let parent = opt(parent).ok_or_else(syntax)?;
+                let mv = if m == "-" { None } else { Some(mv(m).ok_or_else(syntax)?) };
+                let recycles = id(recycles).ok_or_else(syntax)?;
+                let text = match words.get(4..) {
+                    Some(piles) if !piles.is_empty() => piles.join(" "),
+                    _ => return Err(syntax()),
+                };
+                let packed = layout(&text)?.pack()
+                    .map_err(|err| SaveError::Packed { pos:p.nodes.len(), err })?;
+                p.nodes.push(Node {
+                    packed, recycles, parent, mv,
+                    branches : vec![],
+                    redo     : None,
+                });
+            },
+            "branch" => {
+                let [from, to, m] = words[..] else { return Err(syntax()) };
+                let (from, to) = (id(from).ok_or_else(syntax)?, id(to).ok_or_else(syntax)?);
+                let mv = mv(m).ok_or_else(syntax)?;
+                let node = p.nodes.get_mut(from).ok_or_else(syntax)?;
+                node.branches.push(Branch { mv, to, moved: CardSet::default() });
+            },
+            "redo" => {
+                let [at, ib] = words[..] else { return Err(syntax()) };
+                let ib = id(ib).ok_or_else(syntax)?;
+                let node = id(at).and_then(|at| p.nodes.get_mut(at)).ok_or_else(syntax)?;
+                node.redo = Some(ib);
+            },
+            "path" => p.path = ids(rest).ok_or_else(syntax)?,
+            "mark" => {
+                let (path, name) = rest.split_once(' ').unwrap_or((rest, ""));
+                let path = ids(path).ok_or_else(syntax)?;
+                p.marks.push(Mark { name: name.trim().to_string(), path });
+            },
+            "end" => return assemble(p),
+            _ => {},                    // from a later version, skip
+        }
+    }
+    Err(SaveError::Truncated)
+}}
+
+/*----------------------------------------------------------------------
+Binary form
+*/
+
+fn put_u32(out:&mut Vec<u8>, x:usize) {
+    out.extend_from_slice(&(x as u32).to_le_bytes());
+}
+
+fn put_opt(out:&mut Vec<u8>, x:Option<usize>) {
+    put_u32(out, x.unwrap_or(NONE_U32 as usize));
+}
+
+fn put_move(out:&mut Vec<u8>, mv:&Move) {
+    out.extend_from_slice(&[mv.from as u8, mv.to as u8, mv.n as u8]);
+}
+
+fn put_section(out:&mut Vec<u8>, tag:&[u8;4], body:&[u8]) {
+    out.extend_from_slice(tag);
+    put_u32(out, body.len());
+    out.extend_from_slice(body);
+}
+
+impl SaveGame {
+pub fn to_bytes(&self) -> Vec<u8> {
+    let h = &self.history;
+    let mut out:Vec<u8> = vec![];
+    out.extend_from_slice(SAVE_MAGIC);
+    out.extend_from_slice(&(SAVE_VERSION as u16).to_le_bytes());
+
+    put_section(&mut out, b"GAME", self.game.as_bytes());
+    let mut body = vec![];
+    put_u32(&mut body, self.rules.draw);
+    put_u32(&mut body, match self.rules.passes {
+        Passes::Unlimited => 0,
+        Passes::Limited(n) => n,
+    });
+    put_section(&mut out, b"RULE", &body);
+    put_section(&mut out, b"AUTO", &(self.rules.auto_move as u32).to_le_bytes());
+    put_section(&mut out, b"PILE", self.piles.join(" ").as_bytes());
+    put_section(&mut out, b"DECK", &self.deck.cards);
+
+    let mut body = vec![];
+    put_u32(&mut body, h.len());
+    for node in h.nodes() {
+        body.extend_from_slice(&node.packed.to_bytes());
+        put_opt(&mut body, node.parent);
+        put_move(&mut body, &node.mv.unwrap_or(Move { from:0, to:0, n:0 }));
+        put_u32(&mut body, node.recycles);
+        put_opt(&mut body, node.redo);
+        put_u32(&mut body, node.branches.len());
+        for b in &node.branches {
+            put_move(&mut body, &b.mv);
+            put_u32(&mut body, b.to);
+        }
+    }
+    put_section(&mut out, b"NODE", &body);
+
+    let mut body = vec![];
+    put_u32(&mut body, h.path().len());
+    h.path().iter().for_each(|id| put_u32(&mut body, *id));
+    put_section(&mut out, b"PATH", &body);
+
+    let mut body = vec![];
+    put_u32(&mut body, h.marks().len());
+    for m in h.marks() {
+        put_u32(&mut body, m.name.len());
+        body.extend_from_slice(m.name.as_bytes());
+        put_u32(&mut body, m.path.len());
+        m.path.iter().for_each(|id| put_u32(&mut body, *id));
+    }
+    put_section(&mut out, b"MARK", &body);
+    put_section(&mut out, b"END.", &[]);
+    out
+}}
+
+// reads from a byte slice, any read past the end is Truncated
+struct Reader<'a> {
+    bytes : &'a [u8],
+    at    : usize,
+}
+
+impl<'a> Reader<'a> {
+fn take(&mut self, n:usize) -> Result<&'a [u8], SaveError> {
+    if n > self.bytes.len() - self.at {
+        return Err(SaveError::Truncated);
+    }
+    self.at += n;
+    Ok(&self.bytes[self.at - n..self.at])
+}}
+
+impl Reader<'_> {
+fn u32(&mut self) -> Result<usize, SaveError> {
+    let b = self.take(4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}}
+
+impl Reader<'_> {
+fn opt(&mut self) -> Result<Option<usize>, SaveError> {
+    let x = self.u32()?;
+    Ok(if x == NONE_U32 as usize { None } else { Some(x) })
+}}
+
+impl Reader<'_> {
+fn mv(&mut self) -> Result<Move, SaveError> {
+    let b = self.take(3)?;
+    Ok(Move { from:b[0] as usize, to:b[1] as usize, n:b[2] as usize })
+}}
+
+impl Reader<'_> {
+fn string(&mut self, n:usize) -> Result<String, SaveError> {
+    String::from_utf8(self.take(n)?.to_vec())
+        .map_err(|_| SaveError::Invalid("text is not utf-8".to_string()))
+}}
+
+impl Reader<'_> {
+fn done(&self) -> bool {
+    self.at == self.bytes.len()
+}}
+
+impl SaveGame {
+pub fn from_bytes(bytes:&[u8]) -> Result<SaveGame, SaveError> {
+    if !bytes.starts_with(SAVE_MAGIC) {
+        return Err(SaveError::NotSave);
+    }
+    let mut r = Reader { bytes, at: SAVE_MAGIC.len() };
+    let v = r.take(2)?;
+    let version = u16::from_le_bytes([v[0], v[1]]) as u32;
+    if version > SAVE_VERSION {
+        return Err(SaveError::Version(version));
+    }
+    let mut p = Parts {
+        game  : String::new(),
+        piles : vec![],
+        rules : Rules::default(),
+        auto  : true,
+        deck  : Deck { cards: vec![] },
+        nodes : vec![],
+        path  : vec![],
+        marks : vec![],
+    };
+    loop {
+        let tag = r.take(4)?;
+        let len = r.u32()?;
+        let mut s = Reader { bytes: r.take(len)?, at: 0 };
+        match tag {
+            b"GAME" => p.game = s.string(len)?,
+            b"RULE" => {
+                let draw = s.u32()?;
+                let passes = match s.u32()? {
+                    0 => Passes::Unlimited,
+                    n => Passes::Limited(n),
+                };
+                p.rules = Rules { draw, passes, auto_move: true };
+            },
+            b"AUTO" => p.auto = 0 != s.u32()?,
+            b"PILE" => p.piles = s.string(len)?
+                .split_whitespace().map(|s| s.to_string()).collect(),
+            b"DECK" => p.deck = Deck { cards: s.take(len)?.to_vec() },
+            b"NODE" => {
+                for _ in 0..s.u32()? {
+                    let mut packed = [0u8;PACK_SIZE];
+                    packed.copy_from_slice(s.take(PACK_SIZE)?);
+                    let parent = s.opt()?;
+                    let mv = Some(s.mv()?).filter(|_| parent.is_some());
+                    let recycles = s.u32()?;
+                    let redo = s.opt()?;
+                    let mut branches = vec![];
+                    for _ in 0..s.u32()? {
+                        let mv = s.mv()?;
+                        let to = s.u32()?;
+                        branches.push(Branch { mv, to, moved: CardSet::default() });
+                    }
+                    p.nodes.push(Node {
+                        packed: LayoutPacked::from_bytes(packed),
+                        recycles, parent, mv, branches, redo,
+                    });
+                }
+            },
+            b"PATH" => {
+                p.path = (0..s.u32()?).map(|_| s.u32()).collect::<Result<_, _>>()?;
+            },
+            b"MARK" => {
+                for _ in 0..s.u32()? {
+                    let n = s.u32()?;
+                    let name = s.string(n)?;
+                    let path = (0..s.u32()?).map(|_| s.u32()).collect::<Result<_, _>>()?;
+                    p.marks.push(Mark { name, path });
+                }
+            },
+            b"END." => return assemble(p),
+            _ => continue,              // from a later version, skip
+        }
+        if !s.done() {
+            return Err(SaveError::Invalid(format!(
+                "section {} has extra bytes", String::from_utf8_lossy(tag))));
+        }
+    }
+}}
+
+/*----------------------------------------------------------------------
+Files
+
+load_game tells the two forms apart by their first bytes.
+*/
+
+pub fn save_game_text(path:&str, sg:&SaveGame) -> Result<(), SaveError> {
+    std::fs::write(path, sg.to_text())
+        .map_err(|e| SaveError::Io(format!("{}: {}", path, e)))
+}
+
+pub fn save_game_binary(path:&str, sg:&SaveGame) -> Result<(), SaveError> {
+    std::fs::write(path, sg.to_bytes())
+        .map_err(|e| SaveError::Io(format!("{}: {}", path, e)))
+}
+
+pub fn load_game(path:&str) -> Result<SaveGame, SaveError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| SaveError::Io(format!("{}: {}", path, e)))?;
+    if bytes.starts_with(SAVE_MAGIC) {
+        SaveGame::from_bytes(&bytes)
+    } else {
+        let text = String::from_utf8(bytes).map_err(|_| SaveError::NotSave)?;
+        SaveGame::from_text(&text)
+    }
+}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+// a short game with a branch, an undo, a recycle and two marks
+#[cfg(test)]
+fn sample_game() -> SaveGame {
+    let rules = Rules::draw1();
+    let deck = Deck::from_deal(7);
+    let mut h = History::new(&rules.deal(&deck));
+    let play = |h:&mut History, n:usize| {
+        for _ in 0..n {
+            let mv = rules.legal_moves(&h.layout(), h.recycles())[0];
+            h.play(&rules, &mv).unwrap();
+        }
+    };
+    play(&mut h, 5);
+    h.push_mark(None);
+    play(&mut h, 30);
+    h.push_mark(Some("after the recycle"));
+    h.undo();
+    h.undo();
+    let mv = *rules.legal_moves(&h.layout(), h.recycles()).last().unwrap();
+    h.play(&rules, &mv).unwrap();
+    SaveGame::new(&rules, &deck, h)
+}
+
+#[test]
+fn test_save_round_trip() {
+    let sg = sample_game();
+    assert!(sg.history.moves().iter().any(|mv| mv.is_recycle()));
+    assert!(sg.history.nodes().iter().any(|n| n.branches.len() > 1));
+
+    let text = sg.to_text();
+    assert!(text.starts_with("solitaire-save 1\ngame klondike\nrules 1 3\nauto on\n"));
+    assert!(text.contains("\nmark 0,1,2,3,4,5 1\n"));
+    assert_eq!(SaveGame::from_text(&text), Ok(sg.clone()));
+    assert_eq!(SaveGame::from_bytes(&sg.to_bytes()), Ok(sg.clone()));
+
+    // loaded history works, moved cards were worked out again
+    let mut loaded = SaveGame::from_bytes(&sg.to_bytes()).unwrap();
+    let h = &mut loaded.history;
+    assert_eq!(h.goto_mark(1), sg.history.marks()[1].path.last().copied());
+    assert_eq!(h.find(&h.layout(), h.recycles()), Some(h.current()));
+
+    // spacing of a hand edited file does not matter
+    let spaced:String = text.lines()
+        .map(|l| if l.starts_with("pos ") { l.replace(' ', "  ") } else { l.to_string() })
+        .map(|l| l + "\n")
+        .collect();
+    assert_ne!(spaced, text);
+    assert_eq!(SaveGame::from_text(&spaced), Ok(sg.clone()));
+
+    // later versions may add lines and sections we do not know
+    let text2 = text.replace("\nend\n", "\nclock 12:30\nend\n");
+    assert_eq!(SaveGame::from_text(&text2), Ok(sg.clone()));
+    let mut bytes = sg.to_bytes();
+    let end = bytes.len() - 8;
+    bytes.splice(end..end, b"TIME\x02\x00\x00\x00hi".iter().copied());
+    assert_eq!(SaveGame::from_bytes(&bytes), Ok(sg.clone()));
+
+    // rules for purists, and files from before auto move, which had it on
+    let manual = SaveGame { rules: Rules { auto_move: false, ..sg.rules }, ..sg.clone() };
+    let text = manual.to_text();
+    assert!(text.contains("\nrules 1 3\nauto off\n"));
+    assert_eq!(SaveGame::from_text(&text), Ok(manual.clone()));
+    assert_eq!(SaveGame::from_text(&text.replace("auto off\n", "")), Ok(sg.clone()));
+    let bytes = manual.to_bytes();
+    assert_eq!(SaveGame::from_bytes(&bytes), Ok(manual.clone()));
+    // the rules section is as in version 1, auto move a section of its own
+    let at = bytes.windows(4).position(|w| w == b"RULE").unwrap();
+    assert_eq!(&bytes[at+4..at+8], b"\x08\x00\x00\x00");
+    let at = bytes.windows(4).position(|w| w == b"AUTO").unwrap();
+    let mut old = bytes[..at].to_vec();
+    old.extend_from_slice(&bytes[at+12..]);
+    assert_eq!(SaveGame::from_bytes(&old), Ok(sg));
+}
+
+#[test]
+fn test_save_errors() {
+    let sg = sample_game();
+    let text = sg.to_text();
+    let bytes = sg.to_bytes();
+    let t = |text:&str| SaveGame::from_text(text).unwrap_err();
+    let b = |bytes:&[u8]| SaveGame::from_bytes(bytes).unwrap_err();
+
+    assert_eq!(t(""), SaveError::NotSave);
+    assert_eq!(t("hello\n"), SaveError::NotSave);
+    assert_eq!(t(&text.replace("save 1", "save 2")), SaveError::Version(2));
+    assert_eq!(t(&text[..text.len() / 2]), SaveError::Truncated);
+    assert_eq!(t(&text.replace("game klondike", "game spider")),
+        SaveError::UnknownGame("spider".to_string()));
+    assert!(matches!(t(&text.replace("pos 3 2", "pos 3 x")),
+        SaveError::Syntax { line:10, .. }));
+    assert!(matches!(t(&text.replace("rules 1 3", "rules 1")),
+        SaveError::Syntax { line:3, .. }));
+    assert!(matches!(t(&text.replacen(" P24", " P25", 1)),
+        SaveError::Layout { line:7, .. }));
+    assert!(matches!(t(&text.replace("auto on", "auto maybe")),
+        SaveError::Syntax { line:4, .. }));
+    assert!(matches!(t(&text.replace("\npath 0,", "\npath 1,")),
+        SaveError::Invalid(_)));
+    assert!(matches!(t(&text.replace("deck P52", "deck P51").replacen("c3", "", 1)),
+        SaveError::Invalid(_)));
+
+    assert_eq!(b(b"SOL"), SaveError::NotSave);
+    assert_eq!(b(&[&SAVE_MAGIC[..], &[9, 0]].concat()), SaveError::Version(9));
+    for n in [6, 20, bytes.len() / 2, bytes.len() - 1] {
+        assert_eq!(b(&bytes[..n]), SaveError::Truncated, "{}", n);
+    }
+    // scribble on the first packed position
+    let mut bad = bytes.clone();
+    let at = bad.windows(4).position(|w| w == b"NODE").unwrap() + 12;
+    bad[at] = 0x7f;
+    assert!(matches!(b(&bad), SaveError::Packed { pos:0, .. }));
+}
+
+#[test]
+fn test_save_files() {
+    let sg = sample_game();
+    let dir = std::env::temp_dir();
+    let text_path = dir.join("solitaire_test_save.txt");
+    let bin_path = dir.join("solitaire_test_save.bin");
+    let (text_path, bin_path) = (text_path.to_str().unwrap(), bin_path.to_str().unwrap());
+    save_game_text(text_path, &sg).unwrap();
+    save_game_binary(bin_path, &sg).unwrap();
+    assert_eq!(load_game(text_path), Ok(sg.clone()));
+    assert_eq!(load_game(bin_path), Ok(sg));
+    std::fs::remove_file(text_path).unwrap();
+    std::fs::remove_file(bin_path).unwrap();
+    assert!(matches!(load_game(text_path), Err(SaveError::Io(_))));
+}
+
+// end mod save
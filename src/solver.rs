@@ -0,0 +1,619 @@
+/*----------------------------------------------------------------------
+Solver module
+
+Search for a win from any position, and give back the moves.
+
+The search is depth first with iterative deepening.  Each pass goes no
+deeper than a limit on the number of moves, and the limit doubles from
+one pass to the next.  A transposition table, keyed by a zobrist hash
+of the layout, see below, and recycle count, remembers every position
+seen in the pass, and a position is searched at most once in a pass.  This
+also stops cycles, such as drawing through the stock over and over.  The
+table notes whether the search below a position ever came to the depth
+limit.  Reaching such a position again counts as coming to the limit
+too.
+
+Searching each position once, rather than again when it is reached
+with more depth left, means a pass may miss a win within its limit
+that a later pass finds.  It keeps a pass to the size of the table.
+Once the limit is more than the number of positions reachable, no line
+can reach it, so the passes always end.
+
+The canonical layout has the foundations, and the tableau columns,
+sorted, see Rules::canonical.  So a position with piles swapped, such
+as aces played to the foundations in another order, is seen already.
+The key is a hash that is the same for all such layouts, worked out
+from the hashes kept as cards move, without sorting, see
+Rules::canonical_hash.  With unlimited passes the recycle count does
+not change which moves are legal, so it is left out of the key.  The
+key is only a hash, so two positions could share one, and the second
+would be taken as seen.  With 64 bits this is most unlikely, and not
+worth packing every position to check.  Debug builds, and so the
+tests, do keep the packed canonical layout for each key, and panic if
+two positions share one.
+
+The answer is one of three.
+    Win      the moves, from the position given, that win
+    Lost     no win can be reached, however deep
+    Unknown  the budget of nodes or time ran out first
+
+There are two modes, after the papers in refs/.  Thoughtful solitaire
+is played knowing every card, face down or not.  The face up bit still
+limits which moves are legal, but not what the solver may know, so it
+plays right through cards being turned up.  This tells whether a deal
+can be won at all.  Normal solitaire is played knowing only what can
+be seen.  A move that would turn up a face down card, from the tableau
+or by drawing from the stock, ends that line, since the card could be
+anything.  But once the stock has been recycled every card in it has
+been seen in the waste, in an order that does not change, so drawing
+turns up nothing new, as in the hindsight module.  So a win in normal
+mode is certain whatever the hidden cards are.  Both modes share the
+move generation and ordering, and the solution says which mode found
+it.
+
+Lost is only claimed when a whole pass finishes without coming to the
+depth limit, or in normal mode a hidden card, anywhere.  Then every
+position reachable has been searched.  The table is cleared for each
+pass, so this holds.
+
+Moves are tried in order of promise: to foundation, then those that
+turn up a face down card, from the column with most face down first,
+those that leave a card that can go to foundation, waste to tableau,
+emptying a column, the stock, and last other tableau moves and moves off
+foundations.  A card that can go to foundation and could never be needed
+on the tableau, see Rules::is_safe, goes there without trying others,
+unless in normal mode it turns up a hidden card.  Two kinds of move are never tried, because they only give a position
+just like one already there with piles swapped: moving a whole column
+onto an empty column, and moving to any but the first empty foundation
+or the first empty column.
+
+(c) Copyright Bert Douglas 2023.
+SPDX-License-Identifier: AGPL-3.0-or-later
+*/
+
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::klondike::*;
+use crate::layout::*;
+use crate::misc::*;
+
+// Depth limit of the first pass, in moves.  A win from the deal takes
+// well over a hundred moves, counting each draw.
+pub const FIRST_DEPTH:usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Thoughtful,                         // all cards known
+    Normal,                             // only face up cards known
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budget {
+    pub nodes : u64,                    // positions searched
+    pub time  : Option<Duration>,
+}
+
+impl Default for Budget {
+    fn default() -> Budget {
+        Budget { nodes: 1_000_000, time: None }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Vec<Move>),
+    Lost,
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub nodes      : u64,               // positions searched, all passes
+    pub pruned     : u64,               // found in table, not searched
+    pub dead_ends  : u64,               // moves tried that did not win
+    pub positions  : usize,             // size of table, last pass
+    pub depth      : usize,             // depth limit, last pass
+    pub hidden     : u64,               // lines ended at a hidden card
+    pub passes     : usize,
+    pub elapsed    : Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub mode    : Mode,
+    pub outcome : Outcome,
+    pub stats   : Stats,
+}
+
+enum Step {
+    Win,
+    NoWin,
+    OutOfBudget,
+}
+
+struct Search<'a> {
+    rules  : &'a Rules,
+    mode   : Mode,
+    budget : &'a Budget,
+    start  : Instant,
+    table  : PassThroughMap<u64, Seen>,
+    exact  : PassThroughMap<u64, (LayoutPacked, usize)>,   // debug only
+    line   : Vec<Move>,
+    cuts   : u64,                       // times stopped short
+    limits : u64,                       // of those, by depth limit
+    stats  : Stats,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Seen {
+    OnLine,                             // being searched now, further up
+    Done,                               // searched to the end, no win
+    Cut,                                // no win, but stopped short
+}
+
+/*----------------------------------------------------------------------
+Move ordering
+*/
+
+// higher is tried first, None is never tried
+pub(crate) fn score(lo:&Layout, mv:&Move) -> Option<i32> {
+    let src = lo.pile(mv.from);
+    let from_tableau = matches!(role(mv.from), Role::Tableau(_));
+    let empties = from_tableau && src.len() == mv.n;
+    let under = src.len().checked_sub(mv.n + 1).map(|i| src[i]);
+    let reveals = from_tableau && under.is_some_and(|c| !c.face_up());
+    let face_down = src.iter().filter(|c| !c.face_up()).count() as i32;
+    // the card left on top could go up next
+    let frees = from_tableau && under.is_some_and(|c| c.face_up()
+        && (FOUNDATION..TABLEAU).any(|f| match lo.pile(f).last() {
+            Some(top) => top.suit() == c.suit() && top.rank_next(c),
+            None => c.is_ace(),
+        }));
+    let to_empty = lo.pile(mv.to).is_empty();
+    let score = match (role(mv.from), role(mv.to)) {
+        (_, Role::Foundation(i)) => {
+            if to_empty && (FOUNDATION..FOUNDATION+i).any(|f| lo.pile(f).is_empty()) {
+                return None;
+            }
+            if reveals { 110 + face_down } else { 100 }
+        },
+        (_, Role::Tableau(i)) if to_empty
+            && (TABLEAU..TABLEAU+i).any(|t| lo.pile(t).is_empty()) => return None,
+        (Role::Tableau(_), Role::Tableau(_)) => {
+            if empties && to_empty {
+                return None;
+            }
+            if reveals { 80 + face_down } else if frees { 70 }
+            else if empties { 40 } else { 10 }
+        },
+        (Role::Waste, _) => 50,
+        (Role::Stock, _) => 20,
+        (_, Role::Stock) => 15,
+        _ => 0,
+    };
+    Some(score)
+}
+
+// move turns up a card not seen before, from the stock before the
+// first recycle or under a tableau tail
+pub fn turns_up(lo:&Layout, mv:&Move, recycles:usize) -> bool {
+    let src = lo.pile(mv.from);
+    match role(mv.from) {
+        Role::Stock => 0 == recycles,
+        Role::Tableau(_) => src.len() > mv.n && !src[src.len() - mv.n - 1].face_up(),
+        _ => false,
+    }
+}
+
+pub fn ordered_moves(rules:&Rules, lo:&Layout, recycles:usize, mode:Mode) -> Vec<Move> {
+    let moves = rules.legal_moves(lo, recycles);
+    // a safe move can never be wrong, so it is the only one tried, but
+    // not one that ends the line at a hidden card
+    let hides = |mv:&Move| Mode::Normal == mode && turns_up(lo, mv, recycles);
+    if let Some(mv) = moves.iter().find(|mv| rules.is_safe(lo, mv) && !hides(mv)) {
+        return vec![*mv];
+    }
+    let mut scored:Vec<(i32, Move)> = moves.into_iter()
+        .filter_map(|mv| score(lo, &mv).map(|s| (s, mv)))
+        .collect();
+    scored.sort_by_key(|(s, _)| -s);
+    scored.into_iter().map(|(_, mv)| mv).collect()
+}
+
+/*----------------------------------------------------------------------
+Search
+*/
+
+impl Search<'_> {
+fn out_of_budget(&self) -> bool {
+    if self.stats.nodes >= self.budget.nodes {
+        return true;
+    }
+    match self.budget.time {
+        Some(t) if self.stats.nodes.is_multiple_of(1024) => self.start.elapsed() >= t,
+        _ => false,
+    }
+}}
+
+// recycles as far as they matter to the key
+impl Search<'_> {
+fn counted(&self, recycles:usize) -> usize {
+    match self.rules.passes {
+        Passes::Unlimited => 0,
+        Passes::Limited(_) => recycles,
+    }
+}}
+
+impl Search<'_> {
+fn key(&self, lo:&Layout, recycles:usize) -> u64 {
+    self.rules.canonical_hash(lo) ^ zobrist_count(self.counted(recycles))
+}}
+
+// in debug builds, that no two positions share the key
+impl Search<'_> {
+fn check_key(&mut self, key:u64, lo:&Layout, recycles:usize) {
+    if cfg!(debug_assertions) {
+        let real = lo.canonical_order(&self.rules.symmetric_piles());
+        let packed = lo.pack_permuted(&real).expect("klondike layout packs");
+        let position = (packed, self.counted(recycles));
+        assert_eq!(*self.exact.entry(key).or_insert(position), position,
+            "positions share a key");
+    }
+}}
+
+impl Search<'_> {
+fn dfs(&mut self, lo:&Layout, recycles:usize, left:usize) -> Step {
+    if self.rules.is_won(lo) {
+        return Step::Win;
+    }
+    if 0 == left {
+        self.cuts += 1;
+        self.limits += 1;
+        return Step::NoWin;
+    }
+    let key = self.key(lo, recycles);
+    self.check_key(key, lo, recycles);
+    if let Some(seen) = self.table.get(&key) {
+        // what was cut short before is cut short here too
+        if Seen::Cut == *seen {
+            self.cuts += 1;
+        }
+        self.stats.pruned += 1;
+        return Step::NoWin;
+    }
+    if self.out_of_budget() {
+        return Step::OutOfBudget;
+    }
+    self.table.insert(key, Seen::OnLine);
+    self.stats.nodes += 1;
+    let cuts = self.cuts;
+    for mv in ordered_moves(self.rules, lo, recycles, self.mode) {
+        if Mode::Normal == self.mode && turns_up(lo, &mv, recycles) {
+            self.stats.hidden += 1;
+            self.cuts += 1;
+            continue;
+        }
+        let mut next = lo.clone();
+        self.rules.apply(&mut next, recycles, &mv).unwrap();
+        self.line.push(mv);
+        match self.dfs(&next, recycles + mv.is_recycle() as usize, left - 1) {
+            Step::NoWin => self.stats.dead_ends += 1,
+            step => return step,
+        }
+        self.line.pop();
+    }
+    self.table.insert(key, if cuts == self.cuts { Seen::Done } else { Seen::Cut });
+    Step::NoWin
+}}
+
+impl Rules {
+pub fn solve(&self, lo:&Layout, recycles:usize, mode:Mode, budget:&Budget)
+    -> Solution
+{
+    let mut s = Search {
+        rules  : self,
+        mode,
+        budget,
+        start  : Instant::now(),
+        table  : PassThroughMap::default(),
+        exact  : PassThroughMap::default(),
+        line   : vec![],
+        cuts   : 0,
+        limits : 0,
+        stats  : Stats::default(),
+    };
+    let mut depth = FIRST_DEPTH;
+    let outcome = loop {
+        s.table.clear();
+        s.exact.clear();
+        s.line.clear();
+        s.cuts = 0;
+        s.limits = 0;
+        s.stats.depth = depth;
+        s.stats.passes += 1;
+        let step = s.dfs(lo, recycles, depth);
+        s.stats.positions = s.table.len();
+        match step {
+            Step::Win => break Outcome::Win(s.line.clone()),
+            Step::OutOfBudget => break Outcome::Unknown,
+            Step::NoWin if 0 == s.cuts => break Outcome::Lost,
+            // going deeper will not show hidden cards
+            Step::NoWin if 0 == s.limits => break Outcome::Unknown,
+            Step::NoWin => depth *= 2,
+        }
+    };
+    s.stats.elapsed = s.start.elapsed();
+    Solution { mode, outcome, stats: s.stats }
+}}
+
+/*----------------------------------------------------------------------
+Many at once
+
+Positions are solved on several threads, each thread taking the next
+position not yet started.  Each search is on its own, one thread, with
+its own table.  So with a node budget the solutions are the same
+whatever the number of threads, and they come back in the order given.
+Not so with a time budget, since how far a search gets in the time
+depends on the load.
+
+progress is called on the calling thread as each solution comes in,
+with the count done and the total.  threads 0 uses every core.
+*/
+
+pub fn thread_count(threads:usize) -> usize {
+    match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+impl Rules {
+pub fn solve_many(&self, positions:&[(Layout, usize)], mode:Mode, budget:&Budget,
+    threads:usize, progress:&mut dyn FnMut(usize, usize)) -> Vec<Solution>
+{
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let mut out:Vec<Option<Solution>> = positions.iter().map(|_| None).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..thread_count(threads).min(positions.len()) {
+            let (tx, next) = (tx.clone(), &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((lo, recycles)) = positions.get(i) else { break };
+                let sol = self.solve(lo, *recycles, mode, budget);
+                if tx.send((i, sol)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        for (done, (i, sol)) in rx.iter().enumerate() {
+            out[i] = Some(sol);
+            progress(done + 1, positions.len());
+        }
+    });
+    out.into_iter().map(|sol| sol.expect("every position solved")).collect()
+}}
+
+/*----------------------------------------------------------------------
+Tests
+*/
+
+// moves play legally from the position and end in a win
+#[cfg(test)]
+fn check_win(rules:&Rules, lo:&Layout, recycles:usize, moves:&[Move]) {
+    let mut lo = lo.clone();
+    let mut recycles = recycles;
+    for mv in moves {
+        rules.apply(&mut lo, recycles, mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+    }
+    assert!(rules.is_won(&lo));
+}
+
+#[test]
+fn test_solve_end_game() {
+    // kings buried under their queens, one card in the stock
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P1hq P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2skSQ P2hkCQ P2ckDQ P1DK P0P0P0").unwrap();
+    let sol = rules.solve(&lo, 0, Mode::Thoughtful, &Budget::default());
+    let Outcome::Win(moves) = sol.outcome else { panic!("{:?}", sol) };
+    check_win(&rules, &lo, 0, &moves);
+    assert_eq!(sol.stats.passes, 1);
+
+    // already won, nothing to do
+    let mut won = lo.clone();
+    for mv in moves {
+        rules.apply(&mut won, 0, &mv).unwrap();
+    }
+    assert_eq!(rules.solve(&won, 0, Mode::Thoughtful, &Budget::default()).outcome, Outcome::Win(vec![]));
+}
+
+#[test]
+fn test_solve_lost() {
+    // a card is missing, so no win, and the search runs out of positions
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P4c3h5d9s2 P0 P1SA P0P0P0 P1S3 P2h2C7 P0P0P0P0P1D8").unwrap();
+    let sol = rules.solve(&lo, 0, Mode::Thoughtful, &Budget::default());
+    assert_eq!(sol.outcome, Outcome::Lost);
+    assert!(sol.stats.nodes > 10);
+    assert!(sol.stats.pruned > 0);
+    // the same with foundations and columns swapped, see Rules::canonical
+    let mut order:Vec<usize> = (0..N_PILES).collect();
+    order.swap(FOUNDATION, FOUNDATION + 2);
+    order.swap(TABLEAU, TABLEAU + 5);
+    let swapped = rules.solve(&lo.permuted(&order), 0, Mode::Thoughtful, &Budget::default());
+    assert_eq!((swapped.outcome, swapped.stats.positions), (sol.outcome, sol.stats.positions));
+
+    // too small a budget to be sure
+    let sol = rules.solve(&lo, 0, Mode::Thoughtful, &Budget { nodes: 5, time: None });
+    assert_eq!(sol.outcome, Outcome::Unknown);
+}
+
+#[test]
+fn test_solve_not_safe() {
+    // the five of hearts could go up, but the four of spades must come
+    // down onto it to hold the three of diamonds, which hides the two
+    let rules = Rules::default();
+    let lo = Layout::from_text(
+        "P0 P0 P4SAS2S3S4 P4CAC2C3C4 P4HAH2H3H4 P1DA P1H5 P2d2D3 \
+         P36ckskhkdkcqsqhqdqcjsjhjdjctsthtdtc9s9h9d9c8s8h8d8c7s7h7d7c6s6h6d6c5s5d5D4 \
+         P0P0P0P0").unwrap();
+    let h5 = Move { from: TABLEAU, to: FOUNDATION+2, n: 1 };
+    assert!(rules.check(&lo, 0, &h5).is_ok() && !rules.is_safe(&lo, &h5));
+    let sol = rules.solve(&lo, 0, Mode::Thoughtful, &Budget::default());
+    let Outcome::Win(moves) = sol.outcome else { panic!("{:?}", sol) };
+    check_win(&rules, &lo, 0, &moves);
+    // with the five up first it is lost indeed
+    let mut up = lo.clone();
+    rules.apply(&mut up, 0, &h5).unwrap();
+    assert_eq!(rules.solve(&up, 0, Mode::Thoughtful, &Budget::default()).outcome, Outcome::Lost);
+}
+
+#[test]
+fn test_solve_deal() {
+    use crate::deck::*;
+    let rules = Rules::default();
+    let lo = rules.deal(&Deck::from_deal(SOLVED_DEAL));
+    let sol = rules.solve(&lo, 0, Mode::Thoughtful, &Budget::default());
+    let Outcome::Win(moves) = sol.outcome else { panic!("{:?}", sol) };
+    check_win(&rules, &lo, 0, &moves);
+    // no aces shuffled between empty foundations
+    assert!(!moves.iter().any(|mv| matches!(
+        (role(mv.from), role(mv.to)), (Role::Foundation(_), Role::Foundation(_)))));
+}
+
+#[test]
+fn test_solve_modes() {
+    use crate::deck::*;
+    let rules = Rules::default();
+    let budget = Budget::default();
+    let t = |lo:&Layout, mode:Mode| rules.solve(lo, 0, mode, &budget);
+
+    // the deal is won knowing every card, but the first move turns one up
+    let lo = rules.deal(&Deck::from_deal(SOLVED_DEAL));
+    let sol = t(&lo, Mode::Thoughtful);
+    assert!(matches!(sol.outcome, Outcome::Win(_)));
+    assert_eq!(sol.mode, Mode::Thoughtful);
+    assert_eq!(sol.stats.hidden, 0);
+    let sol = t(&lo, Mode::Normal);
+    assert_eq!(sol.outcome, Outcome::Unknown);
+    assert_eq!(sol.mode, Mode::Normal);
+    assert!(sol.stats.hidden > 0);
+
+    // all cards in sight, both modes win the same way
+    let lo = Layout::from_text(
+        "P0 P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2SKHQ P2HKCQ P2CKDQ P2DKSQ P0P0P0").unwrap();
+    let (a, b) = (t(&lo, Mode::Thoughtful), t(&lo, Mode::Normal));
+    assert!(matches!(a.outcome, Outcome::Win(_)));
+    assert_eq!(a.outcome, b.outcome);
+
+    // the queen of hearts is in the stock, normal mode cannot tell
+    let lo = Layout::from_text(
+        "P1hq P0 \
+         P11SAS2S3S4S5S6S7S8S9STSJ \
+         P11HAH2H3H4H5H6H7H8H9HTHJ \
+         P11CAC2C3C4C5C6C7C8C9CTCJ \
+         P11DAD2D3D4D5D6D7D8D9DTDJ \
+         P2SKSQ P1HK P2CKCQ P2DKDQ P0P0P0").unwrap();
+    assert!(matches!(t(&lo, Mode::Thoughtful).outcome, Outcome::Win(_)));
+    assert_eq!(t(&lo, Mode::Normal).outcome, Outcome::Unknown);
+    // but after a recycle it has been seen, so the win is sure
+    let sol = rules.solve(&lo, 1, Mode::Normal, &budget);
+    let Outcome::Win(moves) = sol.outcome else { panic!("{:?}", sol) };
+    check_win(&rules, &lo, 1, &moves);
+    assert_eq!(sol.stats.hidden, 0);
+
+    // a card is missing, the stock is empty, nothing is hidden: lost
+    let lo = Layout::from_text(
+        "P0 P0 P1SA P0P0P0 P1S3 P2H2C7 P0P0P0P0P1D8").unwrap();
+    assert_eq!(t(&lo, Mode::Normal).outcome, Outcome::Lost);
+}
+
+#[test]
+fn test_ordered_moves() {
+    // the ace is safe, but turns up the five
+    let rules = Rules::default();
+    let lo = Layout::from_text("P1h3 P0 P0P0P0P0 P2d5SA P0P0P0P0P0P0").unwrap();
+    let ace = Move { from: TABLEAU, to: FOUNDATION, n: 1 };
+    assert_eq!(ordered_moves(&rules, &lo, 0, Mode::Thoughtful), [ace]);
+    let moves = ordered_moves(&rules, &lo, 0, Mode::Normal);
+    assert_eq!(moves.len(), 2);
+    assert_eq!(moves[0], ace);
+}
+
+#[test]
+fn test_solve_normal_mid_game() {
+    use crate::deck::*;
+    let rules = Rules::default();
+    let budget = Budget::default();
+    // follow the thoughtful win of the deal until the tableau is all
+    // face up, the stock still face down but seen, having been recycled
+    let mut lo = rules.deal(&Deck::from_deal(SOLVED_DEAL));
+    let Outcome::Win(moves) = rules.solve(&lo, 0, Mode::Thoughtful, &budget).outcome
+        else { panic!() };
+    let mut recycles = 0;
+    for mv in &moves {
+        if (TABLEAU..N_PILES).all(|t| lo.pile(t).iter().all(|c| c.face_up())) {
+            break;
+        }
+        rules.apply(&mut lo, recycles, mv).unwrap();
+        recycles += mv.is_recycle() as usize;
+    }
+    assert!(recycles > 0);
+    assert!(lo.pile(STOCK).len() > 1 && lo.pile(STOCK).iter().all(|c| !c.face_up()));
+    assert!((FOUNDATION..TABLEAU).map(|f| lo.pile(f).len()).sum::<usize>() > 20);
+    let sol = rules.solve(&lo, recycles, Mode::Normal, &budget);
+    let Outcome::Win(moves) = sol.outcome else { panic!("{:?}", sol) };
+    check_win(&rules, &lo, recycles, &moves);
+    assert_eq!(sol.stats.hidden, 0);
+    // the same stock never recycled could hold anything
+    assert_eq!(rules.solve(&lo, 0, Mode::Normal, &budget).outcome, Outcome::Unknown);
+}
+
+// a draw three deal the solver wins quickly
+#[cfg(test)]
+const SOLVED_DEAL:u64 = 1;
+
+#[test]
+fn test_solve_many() {
+    use crate::deck::*;
+    let rules = Rules::default();
+    let budget = Budget { nodes: 2000, time: None };
+    let positions:Vec<(Layout, usize)> = (0..6)
+        .map(|deal| (rules.deal(&Deck::from_deal(deal)), 0))
+        .collect();
+    // the same as one at a time, in order, whatever the threads
+    let one:Vec<Outcome> = positions.iter()
+        .map(|(lo, r)| rules.solve(lo, *r, Mode::Thoughtful, &budget).outcome)
+        .collect();
+    for threads in [0, 1, 2, 4, 16] {
+        let mut calls = vec![];
+        let many = rules.solve_many(&positions, Mode::Thoughtful, &budget, threads,
+            &mut |done, total| calls.push((done, total)));
+        let many:Vec<Outcome> = many.into_iter().map(|sol| sol.outcome).collect();
+        assert_eq!(many, one, "{} threads", threads);
+        assert_eq!(calls, (1..=6).map(|done| (done, 6)).collect::<Vec<_>>());
+    }
+    assert!(matches!(one[..], [Outcome::Win(_), Outcome::Win(_), Outcome::Win(_), ..]));
+    assert_eq!(rules.solve_many(&[], Mode::Thoughtful, &budget, 0, &mut |_, _| {}), []);
+}
+
+// end mod solver